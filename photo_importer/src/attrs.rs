@@ -0,0 +1,137 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
+use filetime::FileTime;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimesMode {
+    // 沿用源文件的访问和修改时间
+    Source,
+    // 修改时间设为拍摄时间
+    Exif,
+}
+
+impl FromStr for TimesMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "source" => Ok(TimesMode::Source),
+            "exif" => Ok(TimesMode::Exif),
+            _ => Err(format!("未知的时间模式：{s}，可选 source、exif")),
+        }
+    }
+}
+
+/// 复制后要保留到目标文件上的属性。
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PreserveOptions {
+    pub times: Option<TimesMode>,
+    pub permissions: bool,
+    pub xattrs: bool,
+}
+
+// 目标文件系统不支持扩展属性时静默跳过
+fn is_unsupported(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::Unsupported || e.raw_os_error() == Some(libc::EOPNOTSUPP)
+}
+
+fn copy_xattrs(source: &Path, destination: &Path) -> io::Result<()> {
+    for name in xattr::list(source)? {
+        if let Some(value) = xattr::get(source, &name)? {
+            match xattr::set(destination, &name, &value) {
+                Err(e) if is_unsupported(&e) => return Ok(()),
+                result => result?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 设置目标文件的时间。需在写入 XMP 等修改文件内容的操作之后调用。
+pub fn apply_times(
+    mode: TimesMode,
+    source: &Path,
+    destination: &Path,
+    date: NaiveDateTime,
+) -> io::Result<()> {
+    match mode {
+        TimesMode::Source => {
+            let metadata = fs::metadata(source)?;
+            filetime::set_file_times(
+                destination,
+                FileTime::from_last_access_time(&metadata),
+                FileTime::from_last_modification_time(&metadata),
+            )
+        }
+        TimesMode::Exif => {
+            // 拍摄时间按本地时间解释，夏令时切换时取较早的一个
+            let Some(time) = Local.from_local_datetime(&date).earliest() else {
+                return Ok(());
+            };
+            filetime::set_file_mtime(destination, FileTime::from_unix_time(time.timestamp(), 0))
+        }
+    }
+}
+
+pub fn apply(
+    options: &PreserveOptions,
+    source: &Path,
+    destination: &Path,
+    date: NaiveDateTime,
+) -> io::Result<()> {
+    if options.permissions {
+        fs::set_permissions(destination, fs::metadata(source)?.permissions())?;
+    }
+    if options.xattrs {
+        match copy_xattrs(source, destination) {
+            Err(e) if is_unsupported(&e) => {}
+            result => result?,
+        }
+    }
+    if let Some(mode) = options.times {
+        apply_times(mode, source, destination, date)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dt;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn copies_times_and_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, destination) = (dir.path().join("a.jpg"), dir.path().join("b.jpg"));
+        fs::write(&source, "a").unwrap();
+        fs::write(&destination, "a").unwrap();
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o640)).unwrap();
+        filetime::set_file_mtime(&source, FileTime::from_unix_time(1_000_000_000, 0)).unwrap();
+
+        let options = PreserveOptions {
+            times: Some(TimesMode::Source),
+            permissions: true,
+            xattrs: true,
+        };
+        let date = NaiveDateTime::default();
+        apply(&options, &source, &destination, date).unwrap();
+        let metadata = fs::metadata(&destination).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        assert_eq!(
+            FileTime::from_last_modification_time(&metadata).unix_seconds(),
+            1_000_000_000
+        );
+
+        let date = dt("2024-05-01T14:30:00");
+        apply_times(TimesMode::Exif, &source, &destination, date).unwrap();
+        let mtime = FileTime::from_last_modification_time(&fs::metadata(&destination).unwrap());
+        assert_eq!(
+            mtime.unix_seconds(),
+            Local.from_local_datetime(&date).unwrap().timestamp()
+        );
+    }
+}
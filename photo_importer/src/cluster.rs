@@ -0,0 +1,83 @@
+use crate::geo::distance_km;
+use chrono::{Duration, NaiveDateTime};
+
+pub const EVENT_FMT: &str = "%Y-%m-%d_%H%M";
+
+/// 把照片按拍摄时间分组为事件。
+pub struct ClusterOptions {
+    // 相邻两张照片间隔超过该时长即开始新事件
+    pub max_gap: Duration,
+    // 设置后，相邻照片的 GPS 距离超过该值（公里）也开始新事件
+    pub max_distance_km: Option<f64>,
+}
+
+/// 为每个元素返回其所属事件的开始时间，顺序与输入一致。
+///
+/// `items` 为 (拍摄时间, GPS) 对；没有 GPS 的照片只按时间分组。
+pub fn cluster_events(
+    items: &[(NaiveDateTime, Option<(f64, f64)>)],
+    options: &ClusterOptions,
+) -> Vec<NaiveDateTime> {
+    let mut order = (0..items.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| items[*i].0);
+
+    let mut ret = vec![NaiveDateTime::default(); items.len()];
+    let mut event_start: Option<NaiveDateTime> = None;
+    let mut last_time = NaiveDateTime::default();
+    let mut last_gps: Option<(f64, f64)> = None;
+    for i in order {
+        let (time, gps) = items[i];
+        let far_away = match (options.max_distance_km, last_gps, gps) {
+            (Some(max), Some(last), Some(cur)) => distance_km(last, cur) > max,
+            _ => false,
+        };
+        if event_start.is_none() || time - last_time > options.max_gap || far_away {
+            event_start = Some(time);
+            last_gps = None;
+        }
+        last_time = time;
+        last_gps = gps.or(last_gps);
+        ret[i] = event_start.unwrap();
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dt;
+
+    #[test]
+    fn split_by_gap_and_distance() {
+        let items = [
+            (dt("2024-05-01T15:00:00"), None),
+            (dt("2024-05-01T09:00:00"), Some((31.2, 121.5))),
+            (dt("2024-05-01T10:30:00"), Some((31.2, 121.5))),
+            (dt("2024-05-01T11:00:00"), Some((39.9, 116.4))),
+            (dt("2024-05-01T16:00:00"), None),
+        ];
+        let by_time = ClusterOptions {
+            max_gap: Duration::hours(2),
+            max_distance_km: None,
+        };
+        assert_eq!(
+            cluster_events(&items, &by_time),
+            [
+                dt("2024-05-01T15:00:00"),
+                dt("2024-05-01T09:00:00"),
+                dt("2024-05-01T09:00:00"),
+                dt("2024-05-01T09:00:00"),
+                dt("2024-05-01T15:00:00"),
+            ]
+        );
+
+        let by_distance = ClusterOptions {
+            max_gap: Duration::hours(2),
+            max_distance_km: Some(10.0),
+        };
+        assert_eq!(
+            cluster_events(&items, &by_distance)[3],
+            dt("2024-05-01T11:00:00")
+        );
+    }
+}
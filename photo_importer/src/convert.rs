@@ -0,0 +1,158 @@
+#![cfg_attr(not(feature = "exiv2"), allow(dead_code, unused_imports))]
+
+use crate::layout::Layout;
+use crate::thumbnail;
+#[cfg(feature = "exiv2")]
+use rexiv2::{MediaType, Metadata, Orientation};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+const JPEG_QUALITY: u8 = 92;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConvertKind {
+    // HEIC/HEIF 完整解码为 JPEG，需要 libheif 的 heif-convert 命令
+    Heic,
+    // RAW 取最大的内嵌预览作为 JPEG 代理
+    Raw,
+}
+
+impl FromStr for ConvertKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "heic" => Ok(ConvertKind::Heic),
+            "raw" => Ok(ConvertKind::Raw),
+            _ => Err(format!("未知的转换类型：{s}，可选 heic、raw")),
+        }
+    }
+}
+
+impl ConvertKind {
+    pub fn matches(self, path: &Path) -> bool {
+        match self {
+            ConvertKind::Heic => path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("heic") || e.eq_ignore_ascii_case("heif")),
+            ConvertKind::Raw => thumbnail::is_raw(path),
+        }
+    }
+}
+
+/// 一条转换规则：`heic`、`raw`，或带有 JPEG 存放目录模板的 `raw=proxies/%Y/%Y-%m-%d`。
+/// 模板相对于目标根目录；不给时 JPEG 与原文件放在同一目录。
+#[derive(Clone, Debug)]
+pub struct ConvertRule {
+    pub kind: ConvertKind,
+    pub layout: Option<Layout>,
+}
+
+impl FromStr for ConvertRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, layout) = match s.split_once('=') {
+            Some((kind, template)) => (kind, Some(Layout::new(template)?)),
+            None => (s, None),
+        };
+        Ok(ConvertRule {
+            kind: kind.parse()?,
+            layout,
+        })
+    }
+}
+
+#[cfg(feature = "exiv2")]
+fn convert_heic(source: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("heif-convert")
+        .arg("-q")
+        .arg(JPEG_QUALITY.to_string())
+        .arg(source)
+        .arg(target)
+        .output()
+        .map_err(|e| format!("无法运行 heif-convert: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "heif-convert 失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "exiv2")]
+fn convert_raw(metadata: &Metadata, target: &Path) -> Result<(), Box<dyn Error>> {
+    let previews = metadata.get_preview_images().unwrap_or_default();
+    let largest = previews
+        .iter()
+        .max_by_key(|p| p.get_width() * p.get_height())
+        .ok_or("没有内嵌预览")?;
+    let data = largest.get_data()?;
+    // 内嵌预览通常已是 JPEG，直接写出避免再次压缩
+    match largest.get_media_type() {
+        Ok(MediaType::Jpeg) => fs::write(target, data)?,
+        _ => {
+            let image = image::load_from_memory(&data)?.into_rgb8();
+            let mut file = fs::File::create(target)?;
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, JPEG_QUALITY)
+                .encode_image(&image)?;
+        }
+    }
+    Ok(())
+}
+
+/// 把 `source` 转换为 JPEG 写入 `target`，并复制原文件的 EXIF、IPTC 和 XMP。
+#[cfg(feature = "exiv2")]
+pub fn convert(kind: ConvertKind, source: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    let metadata = Metadata::new_from_path(source)?;
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        match kind {
+            ConvertKind::Heic => convert_heic(source, target)?,
+            ConvertKind::Raw => convert_raw(&metadata, target)?,
+        }
+        let (width, height) = image::ImageReader::open(target)?
+            .with_guessed_format()?
+            .into_dimensions()?;
+        // 原文件的缩略图和尺寸不适用于转换结果
+        metadata.erase_thumbnail();
+        metadata.set_tag_numeric("Exif.Photo.PixelXDimension", width as i32)?;
+        metadata.set_tag_numeric("Exif.Photo.PixelYDimension", height as i32)?;
+        // heif-convert 已按旋转信息输出正向图像；RAW 预览未旋转，保留原方向
+        if kind == ConvertKind::Heic {
+            metadata.set_orientation(Orientation::Normal);
+        }
+        metadata.save_to_file(target)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    result
+}
+
+#[cfg(not(feature = "exiv2"))]
+pub fn convert(_kind: ConvertKind, _source: &Path, _target: &Path) -> Result<(), Box<dyn Error>> {
+    Err("编译时未启用 exiv2 功能，无法转换".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        let rule = "raw=proxies/%Y".parse::<ConvertRule>().unwrap();
+        assert_eq!(rule.kind, ConvertKind::Raw);
+        assert!(rule.layout.is_some());
+        let rule = "heic".parse::<ConvertRule>().unwrap();
+        assert!(rule.layout.is_none());
+        assert!(rule.kind.matches(Path::new("IMG_0001.HEIC")));
+        assert!(!rule.kind.matches(Path::new("IMG_0001.JPG")));
+        assert!("png".parse::<ConvertRule>().is_err());
+    }
+}
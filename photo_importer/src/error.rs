@@ -0,0 +1,101 @@
+#[cfg(feature = "exiftool")]
+use exiftool::ExifToolError;
+use std::fmt;
+use std::io;
+
+/// 读取单个文件时的错误。只影响这一个文件，类别会写入导入报告。
+#[derive(Debug)]
+pub enum ImportError {
+    /// 文件可以读取，但其中没有拍摄时间
+    MetadataUnavailable(String),
+    /// exiv2 和 exiftool 都无法识别的文件
+    UnsupportedFormat(String),
+    /// 拍摄时间格式无法解析
+    Parse(String),
+    Io(io::Error),
+    /// 需要用 exiftool 读取，但找不到或无法启动
+    #[cfg(feature = "exiv2")]
+    ExifToolMissing(io::Error),
+}
+
+impl ImportError {
+    /// 报告中的跳过原因类别
+    pub fn category(&self) -> &'static str {
+        match self {
+            ImportError::MetadataUnavailable(_) => "缺少拍摄时间",
+            ImportError::UnsupportedFormat(_) => "格式不支持",
+            ImportError::Parse(_) => "时间格式错误",
+            ImportError::Io(_) => "读取失败",
+            #[cfg(feature = "exiv2")]
+            ImportError::ExifToolMissing(_) => "缺少 exiftool",
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::MetadataUnavailable(detail)
+            | ImportError::UnsupportedFormat(detail)
+            | ImportError::Parse(detail) => write!(f, "{}: {}", self.category(), detail),
+            ImportError::Io(e) => write!(f, "{}: {}", self.category(), e),
+            #[cfg(feature = "exiv2")]
+            ImportError::ExifToolMissing(e) => write!(f, "{}: {}", self.category(), e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            #[cfg(feature = "exiv2")]
+            ImportError::ExifToolMissing(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+#[cfg(feature = "exiftool")]
+impl From<ExifToolError> for ImportError {
+    fn from(e: ExifToolError) -> Self {
+        match e {
+            ExifToolError::ExifToolNotFound(e) => ImportError::ExifToolMissing(e),
+            ExifToolError::FileNotFound { path, .. } => ImportError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                path.display().to_string(),
+            )),
+            ExifToolError::TagNotFound { tag, .. } => ImportError::MetadataUnavailable(tag),
+            ExifToolError::ExifToolProcess { message, .. } => {
+                ImportError::UnsupportedFormat(message)
+            }
+            e => ImportError::MetadataUnavailable(e.to_string()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "exiftool"))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn categorizes_exiftool_errors() {
+        let missing = ExifToolError::ExifToolNotFound(io::ErrorKind::NotFound.into());
+        assert_eq!(ImportError::from(missing).category(), "缺少 exiftool");
+        let no_tag = ExifToolError::TagNotFound {
+            path: PathBuf::from("a.mp4"),
+            tag: "DateTimeOriginal".to_string(),
+        };
+        assert_eq!(
+            ImportError::from(no_tag).to_string(),
+            "缺少拍摄时间: DateTimeOriginal"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 存储卡、U 盘上常见的无关目录和文件。
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    ".Trashes",
+    ".thumbnails",
+    "MISC",
+    "._*",
+    "$RECYCLE.BIN",
+    "__MACOSX",
+];
+
+/// 目录中的忽略文件，每行一个模式，`#` 开头为注释，模式相对于该文件所在目录。
+pub const IGNORE_FILE: &str = ".photoimportignore";
+
+/// 一组排除模式。不含 `/` 的模式匹配任意一级文件或目录名，含 `/` 的匹配从根开始的相对路径。
+#[derive(Clone, Default)]
+pub struct Excludes {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Excludes {
+    pub fn new(patterns: &[&str]) -> Result<Excludes, Box<dyn Error>> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.trim_end_matches('/');
+            match pattern.trim_start_matches('/') {
+                p if p.contains('/') => {
+                    paths.add(GlobBuilder::new(p).literal_separator(true).build()?)
+                }
+                p => names.add(Glob::new(p)?),
+            };
+        }
+        Ok(Excludes {
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    /// 读取忽略文件；文件不存在时返回 `None`。
+    pub fn load(path: &Path) -> Result<Option<Excludes>, Box<dyn Error>> {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        let patterns = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect::<Vec<_>>();
+        Ok(Some(Excludes::new(&patterns)?))
+    }
+
+    /// `relative` 为相对于模式所在根目录的路径；目录被排除时其中的文件也被排除。
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        relative
+            .components()
+            .any(|c| self.names.is_match(c.as_os_str()))
+            || relative.ancestors().any(|p| self.paths.is_match(p))
+    }
+}
+
+/// 扫描本地目录时的选项。
+#[derive(Clone, Default)]
+pub struct ScanOptions {
+    pub excludes: Excludes,
+    pub max_depth: Option<usize>,
+    pub follow_links: bool,
+}
+
+/// 沿途目录中 `.photoimportignore` 的缓存。
+#[derive(Default)]
+pub struct IgnoreFiles {
+    cache: HashMap<PathBuf, Option<Excludes>>,
+}
+
+impl IgnoreFiles {
+    /// `path` 是否被 `root` 到它之间任意一级目录中的忽略文件排除。
+    pub fn is_ignored(&mut self, root: &Path, path: &Path) -> bool {
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(root) {
+                break;
+            }
+            let excludes = self.cache.entry(dir.to_path_buf()).or_insert_with(|| {
+                Excludes::load(&dir.join(IGNORE_FILE)).unwrap_or_else(|e| {
+                    eprintln!("忽略文件有误 {}: {}", dir.join(IGNORE_FILE).display(), e);
+                    None
+                })
+            });
+            if let Some(excludes) = excludes
+                && let Ok(relative) = path.strip_prefix(dir)
+                && excludes.is_excluded(relative)
+            {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_names_paths_and_ignore_files() {
+        let excludes =
+            Excludes::new(&[DEFAULT_EXCLUDES, &["DCIM/999*", "*.tmp"]].concat()).unwrap();
+        assert!(excludes.is_excluded(Path::new(".Trashes/501/DSC_0001.JPG")));
+        assert!(excludes.is_excluded(Path::new("DCIM/100NZ502/._DSC_0001.JPG")));
+        assert!(excludes.is_excluded(Path::new("$RECYCLE.BIN/a.jpg")));
+        assert!(excludes.is_excluded(Path::new("DCIM/999TEMP/a.jpg")));
+        assert!(!excludes.is_excluded(Path::new("DCIM/100NZ502/DSC_0001.JPG")));
+        assert!(!excludes.is_excluded(Path::new("PRIVATE/DCIM/999TEMP/a.jpg")));
+
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("DCIM")).unwrap();
+        fs::write(root.join("DCIM").join(IGNORE_FILE), "# 测试\nscreenshots\n").unwrap();
+        let mut ignore = IgnoreFiles::default();
+        assert!(ignore.is_ignored(root, &root.join("DCIM/screenshots/a.png")));
+        assert!(!ignore.is_ignored(root, &root.join("screenshots/a.png")));
+    }
+}
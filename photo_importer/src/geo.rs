@@ -0,0 +1,165 @@
+use crate::xdg;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+// 超过这个距离就不认为照片拍摄于该城市
+const MAX_MATCH_KM: f64 = 50.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Place {
+    pub city: String,
+    pub country: String,
+}
+
+struct City {
+    name: String,
+    country_code: String,
+    lat: f64,
+    lon: f64,
+}
+
+/// 基于 GeoNames 城市文件（如 `cities1000.txt`）的离线反向地理编码。
+pub struct GeoDatabase {
+    cities: Vec<City>,
+    countries: HashMap<String, String>,
+    // 按 1° 经纬度网格索引城市
+    grid: HashMap<(i32, i32), Vec<usize>>,
+}
+
+pub fn default_cities_path() -> Option<PathBuf> {
+    let data_home = xdg::xdg_dir("XDG_DATA_HOME", ".local/share")?;
+    Some(data_home.join("photo_importer").join("cities1000.txt"))
+}
+
+pub fn distance_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+fn cell_of(lat: f64, lon: f64) -> (i32, i32) {
+    (lat.floor() as i32, lon.floor() as i32)
+}
+
+impl GeoDatabase {
+    /// 读取城市文件；同目录下如有 `countryInfo.txt` 则用它把国家代码转换为国家名。
+    pub fn load(cities_path: &Path) -> io::Result<GeoDatabase> {
+        let mut db = GeoDatabase {
+            cities: Vec::new(),
+            countries: HashMap::new(),
+            grid: HashMap::new(),
+        };
+        for line in BufReader::new(File::open(cities_path)?).lines() {
+            let line = line?;
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() < 9 {
+                continue;
+            }
+            let (Ok(lat), Ok(lon)) = (fields[4].parse::<f64>(), fields[5].parse::<f64>()) else {
+                continue;
+            };
+            db.grid
+                .entry(cell_of(lat, lon))
+                .or_default()
+                .push(db.cities.len());
+            db.cities.push(City {
+                name: fields[1].to_string(),
+                country_code: fields[8].to_string(),
+                lat,
+                lon,
+            });
+        }
+
+        let country_info = cities_path.with_file_name("countryInfo.txt");
+        if let Ok(file) = File::open(country_info) {
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                if line.starts_with('#') {
+                    continue;
+                }
+                let fields = line.split('\t').collect::<Vec<_>>();
+                if fields.len() > 4 {
+                    db.countries
+                        .insert(fields[0].to_string(), fields[4].to_string());
+                }
+            }
+        }
+        Ok(db)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cities.len()
+    }
+
+    pub fn lookup(&self, lat: f64, lon: f64) -> Option<Place> {
+        let (cell_lat, cell_lon) = cell_of(lat, lon);
+        let mut best: Option<(f64, &City)> = None;
+        for d_lat in -1..=1 {
+            for d_lon in -1..=1 {
+                let Some(indexes) = self.grid.get(&(cell_lat + d_lat, cell_lon + d_lon)) else {
+                    continue;
+                };
+                for city in indexes.iter().map(|i| &self.cities[*i]) {
+                    let distance = distance_km((lat, lon), (city.lat, city.lon));
+                    if best.is_none_or(|(best_distance, _)| distance < best_distance) {
+                        best = Some((distance, city));
+                    }
+                }
+            }
+        }
+        let (distance, city) = best?;
+        if distance > MAX_MATCH_KM {
+            return None;
+        }
+        Some(Place {
+            city: city.name.clone(),
+            country: self
+                .countries
+                .get(&city.country_code)
+                .cloned()
+                .unwrap_or_else(|| city.country_code.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn lookup_nearest_city() {
+        let dir = tempfile::tempdir().unwrap();
+        let cities = dir.path().join("cities1000.txt");
+        let mut file = File::create(&cities).unwrap();
+        writeln!(
+            file,
+            "1816670\tBeijing\tBeijing\t\t39.9075\t116.39723\tP\tPPLC\tCN"
+        )
+        .unwrap();
+        writeln!(
+            file,
+            "1796236\tShanghai\tShanghai\t\t31.22222\t121.45806\tP\tPPLA\tCN"
+        )
+        .unwrap();
+        let mut file = File::create(dir.path().join("countryInfo.txt")).unwrap();
+        writeln!(file, "#ISO\tISO3\tISO-Numeric\tfips\tCountry").unwrap();
+        writeln!(file, "CN\tCHN\t156\tCH\tChina").unwrap();
+
+        let db = GeoDatabase::load(&cities).unwrap();
+        assert_eq!(db.len(), 2);
+        assert_eq!(
+            db.lookup(31.2, 121.5),
+            Some(Place {
+                city: "Shanghai".to_string(),
+                country: "China".to_string()
+            })
+        );
+        assert_eq!(db.lookup(0.0, 0.0), None);
+    }
+}
@@ -0,0 +1,177 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// 导入记录中的一行。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Started {
+        time: NaiveDateTime,
+        source: PathBuf,
+        // 来源卷名，reorganize 没有
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        volume: Option<String>,
+    },
+    Copied {
+        source: PathBuf,
+        destination: PathBuf,
+        // 源文件内容的 blake3 哈希
+        #[serde(default)]
+        hash: String,
+    },
+    // 导入过程中新建的文件，如 XMP 附属文件
+    Created {
+        path: PathBuf,
+        hash: String,
+    },
+    // 导入过程中修改过的文件（如写入了 XMP），hash 为修改后的内容
+    Updated {
+        path: PathBuf,
+        hash: String,
+    },
+    // 导入过程中新建的目录，撤销时若为空则删除
+    CreatedDir {
+        path: PathBuf,
+    },
+    // reorganize 移动的文件
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+        hash: String,
+    },
+}
+
+/// 每次导入在目标目录的 `.photo_importer/imports` 下写一个 JSON Lines 文件。
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+pub fn journal_dir(dst_root: &Path) -> PathBuf {
+    dst_root.join(".photo_importer").join("imports")
+}
+
+// reorganize 的记录另放一处，以免被当作最近一次导入
+pub fn reorganize_dir(dst_root: &Path) -> PathBuf {
+    dst_root.join(".photo_importer").join("reorganize")
+}
+
+// 非本地来源的文件导入前暂存在这里，与目标位于同一文件系统
+pub fn staging_dir(dst_root: &Path) -> PathBuf {
+    dst_root.join(".photo_importer").join("staging")
+}
+
+impl Journal {
+    pub fn create(
+        dst_root: &Path,
+        time: NaiveDateTime,
+        source: &Path,
+        volume: &str,
+    ) -> io::Result<Journal> {
+        Journal::open(
+            &journal_dir(dst_root),
+            JournalEntry::Started {
+                time,
+                source: source.to_path_buf(),
+                volume: Some(volume.to_string()),
+            },
+        )
+    }
+
+    pub fn create_in(dir: &Path, time: NaiveDateTime, source: &Path) -> io::Result<Journal> {
+        Journal::open(
+            dir,
+            JournalEntry::Started {
+                time,
+                source: source.to_path_buf(),
+                volume: None,
+            },
+        )
+    }
+
+    fn open(dir: &Path, started: JournalEntry) -> io::Result<Journal> {
+        let JournalEntry::Started { time, .. } = &started else {
+            return Err(io::Error::other("导入记录须以 Started 开头"));
+        };
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.jsonl", time.format("%Y%m%d-%H%M%S")));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut journal = Journal { path, file };
+        journal.append(&started)?;
+        Ok(journal)
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        let line = serde_json::to_string(entry).map_err(io::Error::other)?;
+        writeln!(self.file, "{line}")
+    }
+
+    /// 同 `fs::create_dir_all`，并记录其中新建的每一级目录。
+    pub fn create_dir_all(&mut self, dir: &Path) -> io::Result<()> {
+        let missing = dir
+            .ancestors()
+            .take_while(|d| !d.as_os_str().is_empty() && !d.exists())
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+        fs::create_dir_all(dir)?;
+        for path in missing.into_iter().rev() {
+            self.append(&JournalEntry::CreatedDir { path })?;
+        }
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// 撤销后改名为 `<名字>.jsonl.undone`，不再参与 `--last-import` 等查找。
+pub fn mark_undone(path: &Path) -> io::Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".undone");
+    let undone = path.with_file_name(name);
+    fs::rename(path, &undone)?;
+    Ok(undone)
+}
+
+pub fn read_journal(path: &Path) -> io::Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str(&line).map_err(io::Error::other)?);
+    }
+    Ok(entries)
+}
+
+/// 按时间顺序列出目标目录下的所有导入记录。
+pub fn list_journals(dst_root: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = journal_dir(dst_root);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut paths = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "jsonl"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+pub fn last_import_time(dst_root: &Path) -> io::Result<Option<NaiveDateTime>> {
+    let Some(last) = list_journals(dst_root)?.pop() else {
+        return Ok(None);
+    };
+    Ok(read_journal(&last)?
+        .into_iter()
+        .find_map(|entry| match entry {
+            JournalEntry::Started { time, .. } => Some(time),
+            _ => None,
+        }))
+}
@@ -0,0 +1,118 @@
+use chrono::NaiveDateTime;
+use chrono::format::{Item, StrftimeItems};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+pub const DEFAULT_LAYOUT: &str = "%Y/%Y-%m-%d";
+pub const DEFAULT_EVENT_LAYOUT: &str = "%Y/{event}";
+const UNKNOWN: &str = "未知";
+
+/// 目标目录模板：chrono 的 strftime 格式，加上 `{name}` 形式的占位符。
+#[derive(Clone, Debug)]
+pub struct Layout {
+    template: String,
+}
+
+// 占位符的值来自照片元数据，不能让其中的 `/`、`%` 影响目录结构和时间格式
+fn sanitize(value: &str) -> String {
+    value.trim().replace(['/', '\\'], "_").replace('%', "%%")
+}
+
+// 依次给出模板中的文字和占位符名：(文字, 占位符)
+fn split_placeholders(template: &str) -> Vec<(&str, Option<&str>)> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        parts.push((&rest[..start], Some(&rest[start + 1..start + len])));
+        rest = &rest[start + len + 1..];
+    }
+    parts.push((rest, None));
+    parts
+}
+
+impl Layout {
+    /// 模板中的 strftime 格式无效时（如 `%Q`、末尾单独的 `%`）返回错误。
+    pub fn new(template: &str) -> Result<Layout, String> {
+        let template = template.trim_matches('/').to_string();
+        // 逐段检查，占位符前后的 `%` 不能与替换进来的值拼成格式
+        let invalid = split_placeholders(&template)
+            .into_iter()
+            .any(|(text, _)| StrftimeItems::new(text).any(|item| matches!(item, Item::Error)));
+        if invalid {
+            return Err(format!("无效的模板：{template}"));
+        }
+        Ok(Layout { template })
+    }
+
+    /// 模板中是否含有 `{name}` 占位符。
+    pub fn has_placeholders(&self) -> bool {
+        split_placeholders(&self.template)
+            .iter()
+            .any(|(_, name)| name.is_some())
+    }
+
+    pub fn render(&self, date: &NaiveDateTime, placeholders: &HashMap<&str, String>) -> PathBuf {
+        // 占位符的值不再参与替换，其中的 `{name}` 原样保留
+        let mut template = String::new();
+        for (text, name) in split_placeholders(&self.template) {
+            template.push_str(text);
+            if let Some(name) = name {
+                let value = placeholders
+                    .get(name)
+                    .map(|value| sanitize(value))
+                    .filter(|value| !value.is_empty())
+                    .unwrap_or_else(|| UNKNOWN.to_string());
+                template.push_str(&value);
+            }
+        }
+        PathBuf::from(date.format(&template).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dt;
+
+    #[test]
+    fn render_placeholders() {
+        let date = dt("2024-05-01T14:30:12");
+        let layout = Layout::new("%Y/{country}/%Y-%m-%d {city}").unwrap();
+        let mut placeholders = HashMap::new();
+        placeholders.insert("country", "China".to_string());
+        assert_eq!(
+            layout.render(&date, &placeholders),
+            PathBuf::from("2024/China/2024-05-01 未知")
+        );
+        placeholders.insert("city", "100%/Shanghai".to_string());
+        assert_eq!(
+            layout.render(&date, &placeholders),
+            PathBuf::from("2024/China/2024-05-01 100%_Shanghai")
+        );
+        assert_eq!(
+            Layout::new(DEFAULT_LAYOUT)
+                .unwrap()
+                .render(&date, &HashMap::new()),
+            PathBuf::from("2024/2024-05-01")
+        );
+        // 值中的占位符不会再被替换
+        placeholders.insert("city", "{city}".to_string());
+        assert_eq!(
+            layout.render(&date, &placeholders),
+            PathBuf::from("2024/China/2024-05-01 {city}")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_strftime() {
+        assert!(Layout::new("%Y/%Q").is_err());
+        assert!(Layout::new("%Y/100%").is_err());
+        assert!(Layout::new("%{city}Y").is_err());
+        assert!(Layout::new("%Y/%%{city}").is_ok());
+        assert!(Layout::new("%Y/{city}").unwrap().has_placeholders());
+        assert!(!Layout::new(DEFAULT_LAYOUT).unwrap().has_placeholders());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::rename;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const STILL_EXTS: &[&str] = &["heic", "heif", "jpg", "jpeg"];
+const VIDEO_EXTS: &[&str] = &["mov"];
+
+fn has_ext(path: &Path, exts: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| exts.iter().any(|ext| e.eq_ignore_ascii_case(ext)))
+}
+
+/// 为静态图找到配对的实况视频，返回 静态图 -> 视频。
+///
+/// 实况照片的静态图和视频中都记录了相同的 `ContentIdentifier`（苹果 MakerNote / QuickTime Keys）。
+/// 优先按 `identifier` 返回的 `ContentIdentifier` 配对（编辑过的 `IMG_E1234.HEIC` 也能配上），
+/// 其余的按同一目录下相同的文件名主干配对。没有视频时不会调用 `identifier`。
+pub fn pair(
+    paths: &[PathBuf],
+    mut identifier: impl FnMut(&Path) -> Option<String>,
+) -> HashMap<PathBuf, PathBuf> {
+    let (stills, videos): (Vec<_>, Vec<_>) = paths
+        .iter()
+        .filter(|p| has_ext(p, STILL_EXTS) || has_ext(p, VIDEO_EXTS))
+        .partition(|p| has_ext(p, STILL_EXTS));
+    let mut pairs = HashMap::new();
+    if videos.is_empty() {
+        return pairs;
+    }
+
+    let mut videos_by_id = HashMap::new();
+    for video in &videos {
+        if let Some(id) = identifier(video) {
+            videos_by_id.insert(id, *video);
+        }
+    }
+    if !videos_by_id.is_empty() {
+        for still in &stills {
+            if let Some(id) = identifier(still)
+                && let Some(video) = videos_by_id.remove(&id)
+            {
+                pairs.insert((*still).clone(), video.clone());
+            }
+        }
+    }
+
+    // 没有 ContentIdentifier 时（旧设备、被其他软件导出过）按文件名配对
+    let paired = pairs.values().cloned().collect::<Vec<_>>();
+    let mut videos_by_stem = videos
+        .iter()
+        .filter(|v| !paired.contains(v))
+        .map(|v| (rename::group_key(v), *v))
+        .collect::<HashMap<_, _>>();
+    for still in stills {
+        if !pairs.contains_key(still)
+            && let Some(video) = videos_by_stem.remove(&rename::group_key(still))
+        {
+            pairs.insert(still.clone(), video.clone());
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_by_identifier_then_stem() {
+        let paths = [
+            "DCIM/IMG_0001.HEIC",
+            "DCIM/IMG_0001.MOV",
+            "DCIM/IMG_E0002.HEIC",
+            "DCIM/IMG_0002.MOV",
+            "DCIM/IMG_0003.HEIC",
+            "DCIM/IMG_0004.MOV",
+        ]
+        .map(PathBuf::from);
+        let ids = HashMap::from([
+            (Path::new("DCIM/IMG_E0002.HEIC"), "B"),
+            (Path::new("DCIM/IMG_0002.MOV"), "B"),
+        ]);
+        let pairs = pair(&paths, |p| ids.get(p).map(|id| id.to_string()));
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[&paths[0]], paths[1]);
+        assert_eq!(pairs[&paths[2]], paths[3]);
+        assert!(pair(&paths[..1], |_| unreachable!()).is_empty());
+    }
+}
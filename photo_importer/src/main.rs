@@ -1,41 +1,169 @@
-use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, ParseResult};
-use exiftool::ExifTool;
-use rexiv2::{LogLevel, Metadata};
-use std::collections::HashMap;
-use std::convert::Into;
-use std::io::Write;
+mod attrs;
+mod cluster;
+mod convert;
+mod error;
+mod exclude;
+mod geo;
+mod journal;
+mod layout;
+mod live;
+mod metadata;
+mod profile;
+mod rename;
+mod reorganize;
+mod report;
+mod review;
+mod scheduler;
+mod similar;
+mod skiplist;
+mod source;
+mod space;
+mod takeout;
+#[cfg(test)]
+mod test_util;
+mod thumbnail;
+mod time_range;
+mod undo;
+mod volume;
+mod xdg;
+mod xmp;
+
+use attrs::PreserveOptions;
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
+use convert::ConvertRule;
+use error::ImportError;
+use exclude::{Excludes, ScanOptions};
+use geo::{GeoDatabase, Place};
+use journal::{Journal, JournalEntry};
+use layout::Layout;
+use profile::{Config, TagOptions};
+use report::ReportRow;
+use scheduler::{CopyJob, CopyMethod, IoOptions};
+use skiplist::{SkipEntry, SkipList};
+use source::{Source, Staging};
+use std::collections::{HashMap, HashSet};
+use std::io::{IsTerminal, Write};
 use std::ops::Range;
+use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, Mutex};
 use std::{
     error::Error,
     fs, io,
     path::{Path, PathBuf},
+    process::ExitCode,
 };
-use walkdir::WalkDir;
+use thumbnail::ThumbnailMode;
+use volume::VolumeInfo;
+use xmp::{XmpMode, XmpValue};
+
+/// 一个导入目标：根目录及其目录模板。
+struct Destination {
+    root: PathBuf,
+    layout: Layout,
+}
+
+impl Destination {
+    fn dir_for(&self, image: &ImageInfo) -> PathBuf {
+        // 同一事件跨过午夜或跨年时，仍按事件开始时间放在同一个目录
+        let date = image.event.unwrap_or(image.date);
+        self.root
+            .join(self.layout.render(&date, &image.layout_placeholders()))
+    }
+}
+
+/// 导入过程中的可选处理。
+#[derive(Default)]
+struct ImportOptions {
+    io: IoOptions,
+    thumbnails: Option<ThumbnailMode>,
+    xmp: Option<XmpMode>,
+    // 用户给出的时间校正，如 "+1h"
+    time_shift: Option<String>,
+    import_time: NaiveDateTime,
+    source_volume: String,
+    // 关键词、版权等，来自 --profile 和命令行
+    tags: TagOptions,
+    preserve: PreserveOptions,
+    // 额外生成 JPEG 的规则
+    convert: Vec<ConvertRule>,
+}
 
 #[derive(Clone)]
 struct ImageInfo {
     path: PathBuf,
     date: NaiveDateTime,
+    // (纬度, 经度)
+    gps: Option<(f64, f64)>,
+    place: Option<Place>,
+    // 所属事件的开始时间，仅在分组模式下设置
+    event: Option<NaiveDateTime>,
+    // 感知哈希，仅在查找近似照片时计算
+    phash: Option<u64>,
+    // 所在近似照片组的代表
+    similar_group: Option<PathBuf>,
+    // 重命名后的文件名主干，仅在重命名模式下设置
+    new_stem: Option<String>,
+    // 实况照片配对的视频，随照片导入到同一目录
+    live_video: Option<PathBuf>,
+}
+
+impl ImageInfo {
+    fn location(&self) -> Option<String> {
+        self.place
+            .as_ref()
+            .map(|place| format!("{}, {}", place.city, place.country))
+    }
+
+    fn layout_placeholders(&self) -> HashMap<&'static str, String> {
+        let mut map = HashMap::new();
+        if let Some(place) = &self.place {
+            map.insert("city", place.city.clone());
+            map.insert("country", place.country.clone());
+        }
+        if let Some(event) = &self.event {
+            map.insert("event", event.format(cluster::EVENT_FMT).to_string());
+        }
+        map
+    }
+}
+
+// 读取拍摄时间，`shift` 为用户给出的时间校正
+fn read_image_info(path: &Path, shift: Duration) -> Result<ImageInfo, ImportError> {
+    Ok(ImageInfo {
+        path: path.to_path_buf(),
+        date: get_date_taken(path)? + shift,
+        gps: None,
+        place: None,
+        event: None,
+        phash: None,
+        similar_group: None,
+        new_stem: None,
+        live_video: None,
+    })
 }
 
-fn get_image_infos(images: &[PathBuf]) -> Vec<ImageInfo> {
+// 返回读取成功的照片，以及无法读取而跳过的文件
+// 解析元数据和计算感知哈希主要耗费 CPU，按核数并行；--readers 只限制复制时的读取
+fn get_image_infos(images: &[PathBuf], shift: Duration) -> (Vec<ImageInfo>, Vec<ReportRow>) {
     let shared = Arc::new(Mutex::new(Vec::<ImageInfo>::new()));
+    let skipped = Arc::new(Mutex::new(Vec::<ReportRow>::new()));
     let counter = Arc::new(std::sync::atomic::AtomicIsize::new(0));
-    let pool = threadpool::ThreadPool::default();
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let pool = threadpool::ThreadPool::new(threads);
     for path in images {
         let path = path.clone();
         let shared = shared.clone();
         let total_count_str = images.len().to_string();
         let counter = counter.clone();
+        let skipped = skipped.clone();
         pool.execute(move || {
             let left_adjust = total_count_str.len();
             let get_idx_print = || {
                 let idx = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
                 format!("[{:left_adjust$}/{}] ", idx + 1, total_count_str)
             };
-            let date = match get_date_taken(path.as_path()) {
-                Ok(date) => date,
+            let info = match read_image_info(&path, shift) {
+                Ok(info) => info,
                 Err(e) => {
                     println!(
                         "{} 跳过 {}, 无法获取拍摄时间：{}",
@@ -43,53 +171,163 @@ fn get_image_infos(images: &[PathBuf]) -> Vec<ImageInfo> {
                         path.to_string_lossy(),
                         e
                     );
+                    skipped
+                        .lock()
+                        .unwrap()
+                        .push(skipped_row(&path, None, e.to_string()));
                     return;
                 }
             };
             println!("{} 获取成功：{}", get_idx_print(), path.to_string_lossy());
-            shared.lock().unwrap().push(ImageInfo {
-                path: path.clone(),
-                date,
-            });
+            shared.lock().unwrap().push(info);
         })
     }
     pool.join();
-    std::mem::take(shared.lock().unwrap().as_mut())
+    let infos = std::mem::take(shared.lock().unwrap().as_mut());
+    let skipped = std::mem::take(skipped.lock().unwrap().as_mut());
+    (infos, skipped)
 }
 
-fn scan_photos(path: &Path) -> Vec<PathBuf> {
-    let mut ret = Vec::<PathBuf>::new();
+// 解码整张图片很慢，只为过滤后留下的照片计算感知哈希
+fn compute_phashes(infos: &mut [ImageInfo]) {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = infos.len().div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        for chunk in infos.chunks_mut(chunk_size) {
+            scope.spawn(move || {
+                for info in chunk {
+                    info.phash = similar::compute(&info.path);
+                }
+            });
+        }
+    });
+}
+
+/// 非本地来源的照片逐个取到本地读取，只有在时间范围内、不在跳过列表中的留在本地，其余随即删除，
+/// 不会先把整个压缩包或设备取到本地。
+///
+/// 返回留下的照片、跳过的文件，以及各文件的 ContentIdentifier，供配对实况照片。
+fn probe_images(
+    source: &dyn Source,
+    staging: &Staging,
+    shift: Duration,
+    with_phash: bool,
+    time_range: &Range<NaiveDateTime>,
+    skip_list: &SkipList,
+) -> (Vec<ImageInfo>, Vec<ReportRow>, HashMap<PathBuf, String>) {
+    let mut infos = Vec::new();
+    let mut skipped = Vec::new();
+    let mut identifiers = HashMap::new();
+    let mut count = 0;
+    let result = staging.probe(source, &|path| is_media(path), &mut |path| {
+        count += 1;
+        if let Some(id) = metadata::reader().content_identifier(path) {
+            identifiers.insert(path.to_path_buf(), id);
+        }
+        let info = match read_image_info(path, shift) {
+            Ok(info) => info,
+            Err(e) => {
+                println!("[{count}] 跳过 {}, 无法获取拍摄时间：{}", path.display(), e);
+                skipped.push(skipped_row(path, None, e.to_string()));
+                return false;
+            }
+        };
+        let (mut kept, rows) = filter_images(&[info], time_range, skip_list);
+        skipped.extend(rows);
+        let Some(mut info) = kept.pop() else {
+            return false;
+        };
+        if with_phash {
+            info.phash = similar::compute(path);
+        }
+        println!("[{count}] 获取成功：{}", path.display());
+        infos.push(info);
+        true
+    });
+    match result {
+        Ok(failed) => skipped.extend(failed_rows(failed)),
+        Err(e) => eprintln!("无法读取来源：{e}"),
+    }
+    (infos, skipped, identifiers)
+}
 
-    println!("开始扫描: {path:?} 中的图片...");
-    for entry in WalkDir::new(path)
+// 没能从非本地来源取到的文件也写入报告
+fn failed_rows(failed: Vec<(PathBuf, io::Error)>) -> Vec<ReportRow> {
+    failed
         .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-        // 检查支持的图片格式
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|e| e.to_lowercase())
-            .unwrap_or_default();
-
-        if !matches!(
-            ext.to_lowercase().as_str(),
-            "jpg" | "jpeg" | "rw2" | "dng" | "mp4" | "nef"
-        ) {
+        .map(|(path, e)| skipped_row(&path, None, format!("读取失败：{e}")))
+        .collect()
+}
+
+fn skipped_row(path: &Path, date: Option<NaiveDateTime>, reason: String) -> ReportRow {
+    ReportRow {
+        source: path.to_path_buf(),
+        destination: None,
+        date,
+        location: None,
+        similar_group: None,
+        status: reason,
+    }
+}
+
+// 检查支持的图片格式
+fn is_media(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    matches!(
+        ext.as_str(),
+        "jpg" | "jpeg" | "rw2" | "dng" | "mp4" | "nef" | "heic" | "heif" | "mov"
+    )
+}
+
+// 返回照片的本地路径，以及没能取到的附属文件。非本地来源的照片此时还没有取到 `staging`，
+// XMP 和 Takeout 的 JSON 附属文件很小，先全部取出，读取拍摄时间时要用到
+fn scan_photos(source: &dyn Source, staging: &Staging) -> (Vec<PathBuf>, Vec<ReportRow>) {
+    let mut ret = Vec::<PathBuf>::new();
+    let mut failed = Vec::new();
+
+    println!("开始扫描: {} 中的图片...", source.describe());
+    // 照片本身，以及 XMP 附属文件和 Takeout 的 JSON 附属文件
+    let wanted = |path: &Path| {
+        is_media(path)
+            || takeout::is_takeout_json(path)
+            || path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case(rename::SIDECAR_EXT))
+    };
+    let files = match staging.list(source, &wanted) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("无法读取来源：{e}");
+            return (ret, failed);
+        }
+    };
+    match staging.fetch(source, &|path| !is_media(path)) {
+        Ok(rows) => failed = failed_rows(rows),
+        Err(e) => eprintln!("无法读取来源：{e}"),
+    }
+    for local in files {
+        if !is_media(&local) {
             continue;
         }
 
-        println!("找到: {path:?}");
-        ret.push(path.to_path_buf());
+        println!("找到: {local:?}");
+        ret.push(local);
     }
 
-    ret
+    (ret, failed)
 }
 
-fn filter_images(image_infos: &[ImageInfo], time_range: &Range<NaiveDateTime>) -> Vec<ImageInfo> {
+fn filter_images(
+    image_infos: &[ImageInfo],
+    time_range: &Range<NaiveDateTime>,
+    skip_list: &SkipList,
+) -> (Vec<ImageInfo>, Vec<ReportRow>) {
     let mut ret = Vec::<ImageInfo>::new();
+    let mut skipped = Vec::<ReportRow>::new();
     let mut set = HashMap::<String, &ImageInfo>::new();
     for info in image_infos {
         let file_name = info
@@ -102,216 +340,1374 @@ fn filter_images(image_infos: &[ImageInfo], time_range: &Range<NaiveDateTime>) -
             && contained.date.date() == info.date.date()
         {
             // println!("重复，跳过: {}", info.path.as_path().display());
+            skipped.push(skipped_row(&info.path, Some(info.date), "重复".to_string()));
             continue;
         }
         if !time_range.contains(&info.date) {
             // println!("日期不符，跳过: {}", info.path.as_path().display());
+            skipped.push(skipped_row(
+                &info.path,
+                Some(info.date),
+                "不在时间范围内".to_string(),
+            ));
+            continue;
+        }
+        // 跳过列表为空时不必读取文件计算哈希
+        if !skip_list.is_empty()
+            && let Some(entry) = file_hash(&info.path).and_then(|h| skip_list.get(&h))
+        {
+            skipped.push(skipped_row(
+                &info.path,
+                Some(info.date),
+                format!("已拒绝过（{}）", entry.reason),
+            ));
             continue;
         }
         set.insert(file_name, info);
         ret.push(info.clone());
         // println!("将复制：{}", info.path.as_path().display());
     }
-    ret
+    (ret, skipped)
 }
 
-fn ask_if_continue(question: &str, default: bool) -> bool {
+fn file_hash(path: &Path) -> Option<String> {
+    match scheduler::hash_file(path) {
+        Ok(hash) => Some(hash.to_hex().to_string()),
+        Err(e) => {
+            eprintln!("读取失败 {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// 询问是否继续。`assume_yes` 时直接确认；标准输入不是终端或已关闭时返回错误，
+/// 以便在 cron、systemd、管道等无人值守的环境中退出而不是一直等待。
+fn ask_if_continue(
+    question: &str,
+    default: bool,
+    assume_yes: bool,
+) -> Result<bool, Box<dyn Error>> {
     let options = if default { "[Y/n]" } else { "[y/N]" };
+    if assume_yes {
+        println!("{question}{options}: y（--yes）");
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        println!("{question}{options}");
+        return Err("标准输入不是终端，如需自动确认请使用 --yes".into());
+    }
 
     loop {
         print!("{question}{options}:");
-        io::stdout().flush().unwrap();
+        io::stdout().flush()?;
 
         let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("无法读取输入");
+        if io::stdin().read_line(&mut input)? == 0 {
+            println!();
+            return Err("无法读取输入，如需自动确认请使用 --yes".into());
+        }
         let lower_trimed = input.to_lowercase().trim().to_owned();
         if lower_trimed.is_empty() {
-            return default;
+            return Ok(default);
         }
         if lower_trimed == "y" {
-            return true;
+            return Ok(true);
         }
         if lower_trimed == "n" {
-            return false;
+            return Ok(false);
         }
     }
 }
 
+fn assume_yes(named_args: &HashMap<&str, &str>) -> bool {
+    named_args.contains_key("--yes") || named_args.contains_key("--no-confirm")
+}
+
+// 不带值的开关参数
+const FLAG_ARGS: &[&str] = &[
+    "--last-import",
+    "--find-similar",
+    "--collapse-bursts",
+    "--dry-run",
+    "--review",
+    "--yes",
+    "--no-confirm",
+    "--no-default-excludes",
+    "--follow-symlinks",
+    "--preserve-permissions",
+    "--preserve-xattrs",
+    "--force",
+];
+
 fn get_named_args(args: &[String]) -> HashMap<&str, &str> {
     let mut map = HashMap::<&str, &str>::new();
     let mut current_name: Option<&str> = Option::default();
     for arg in args {
-        if arg.starts_with('-') {
-            current_name = Some(arg.as_str());
-        } else if let Some(cur) = current_name {
+        if let Some(cur) = current_name {
             map.insert(cur, arg.as_str());
             current_name = None;
+        } else if FLAG_ARGS.contains(&arg.as_str()) {
+            map.insert(arg.as_str(), "");
+        } else if arg.starts_with('-') {
+            current_name = Some(arg.as_str());
         }
     }
 
     map
 }
 
-const DATE_FMT: &str = "%Y-%m-%d";
-const DATE_TIME_FMT: &str = "%Y-%m-%dT%H:%M:%S";
+// 可重复出现的参数，按出现顺序返回所有值
+fn get_repeated_args<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|pair| pair[0] == name)
+        .map(|pair| pair[1].as_str())
+        .collect()
+}
 
-fn parse_date_or_datetime(input: &str) -> ParseResult<NaiveDateTime> {
-    println!("{input}");
-    Ok(NaiveDateTime::parse_from_str(input.trim(), DATE_TIME_FMT)
-        .or_else(|_| {
-            NaiveDate::parse_from_str(input.trim(), DATE_FMT)
-                .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
-        })?
-        .into())
+fn get_positional_args(args: &[String]) -> Vec<&str> {
+    let mut ret = Vec::<&str>::new();
+    let mut expect_value = false;
+    for arg in args.iter().skip(1) {
+        if expect_value {
+            expect_value = false;
+        } else if FLAG_ARGS.contains(&arg.as_str()) {
+            continue;
+        } else if arg.starts_with('-') {
+            expect_value = true;
+        } else {
+            ret.push(arg.as_str());
+        }
+    }
+    ret
 }
 
 fn get_input_time_range(
     args: &HashMap<&str, &str>,
+    dst_path: &Path,
 ) -> Result<Range<NaiveDateTime>, Box<dyn Error>> {
+    let now = Local::now().naive_local();
     let far_future = DateTime::UNIX_EPOCH + Duration::days(365 * 3000);
-    let time_from = args.get("--time-from");
-    let time_to = args.get("--time-to");
-    let time_from = time_from
-        .map(|input| parse_date_or_datetime(input))
-        .unwrap_or(Ok(DateTime::UNIX_EPOCH.naive_utc()))?;
-    let time_to = time_to
-        .map(|input| parse_date_or_datetime(input))
-        .unwrap_or(Ok(far_future.naive_utc()))?;
-    Ok(Range {
-        start: time_from,
-        end: time_to,
-    })
+    let mut range = Range {
+        start: DateTime::UNIX_EPOCH.naive_utc(),
+        end: far_future.naive_utc(),
+    };
+    if let Some(input) = args.get("--date") {
+        range = time_range::parse_time_expr(input, now)?;
+    }
+    if args.contains_key("--last-import") {
+        range.start = journal::last_import_time(dst_path)?
+            .ok_or("目标目录中没有导入记录，无法使用 --last-import")?;
+    }
+    if let Some(input) = args.get("--since") {
+        range.start = time_range::parse_time_expr(input, now)?.start;
+    }
+    if let Some(input) = args.get("--time-from") {
+        range.start = time_range::parse_time_expr(input, now)?.start;
+    }
+    if let Some(input) = args.get("--time-to") {
+        range.end = time_range::parse_time_expr(input, now)?.end;
+    }
+    Ok(range)
 }
 
-fn do_import(images: &[ImageInfo], dst_path: &Path) {
-    let pool = threadpool::ThreadPool::default();
-    let counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    for  image in images.iter(){
-        let dst_path = dst_path.to_owned();
-        let total_count_str = images.len().to_string();
-        let image = image.clone();
-        let counter = counter.clone();
-        pool.execute(move || {
-            let left_adjust = total_count_str.len();
-            let get_idx_str = || {
-                let idx = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                format!("[{:left_adjust$}/{}] ", idx + 1, total_count_str)
+// `origin` 为照片在来源中的路径
+fn import_xmp_values(
+    image: &ImageInfo,
+    origin: &Path,
+    options: &ImportOptions,
+) -> Vec<(String, XmpValue)> {
+    let original_name = image.path.file_name().unwrap_or_default();
+    let mut values = vec![
+        (
+            "Xmp.xmpMM.PreservedFileName".to_string(),
+            XmpValue::Text(original_name.to_string_lossy().to_string()),
+        ),
+        (
+            xmp::tag("SourceVolume"),
+            XmpValue::Text(options.source_volume.clone()),
+        ),
+        (
+            xmp::tag("SourcePath"),
+            XmpValue::Text(origin.to_string_lossy().to_string()),
+        ),
+        (
+            xmp::tag("ImportTime"),
+            XmpValue::Text(options.import_time.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        ),
+        (
+            xmp::tag("ImporterVersion"),
+            XmpValue::Text(format!("photo_importer {}", env!("CARGO_PKG_VERSION"))),
+        ),
+    ];
+    if let Some(shift) = &options.time_shift {
+        values.push((xmp::tag("TimeCorrection"), XmpValue::Text(shift.clone())));
+        values.push((
+            "Xmp.exif.DateTimeOriginal".to_string(),
+            XmpValue::Text(image.date.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        ));
+    }
+    values
+}
+
+// 关键词、版权、作者、评分；Exif/IPTC 标签只在写入 JPEG 本身时生效
+fn tag_xmp_values(tags: &TagOptions) -> Vec<(String, XmpValue)> {
+    let mut values = Vec::new();
+    if !tags.keywords.is_empty() {
+        for tag in ["Xmp.dc.subject", "Iptc.Application2.Keywords"] {
+            values.push((tag.to_string(), XmpValue::Bag(tags.keywords.clone())));
+        }
+    }
+    if let Some(copyright) = &tags.copyright {
+        for tag in [
+            "Xmp.dc.rights",
+            "Exif.Image.Copyright",
+            "Iptc.Application2.Copyright",
+        ] {
+            values.push((tag.to_string(), XmpValue::Text(copyright.clone())));
+        }
+    }
+    if let Some(artist) = &tags.artist {
+        for tag in [
+            "Xmp.dc.creator",
+            "Exif.Image.Artist",
+            "Iptc.Application2.Byline",
+        ] {
+            values.push((tag.to_string(), XmpValue::Text(artist.clone())));
+        }
+    }
+    if let Some(rating) = tags.rating {
+        values.push((
+            "Xmp.xmp.Rating".to_string(),
+            XmpValue::Text(rating.to_string()),
+        ));
+    }
+    values
+}
+
+// 把导入过程中新建或修改的文件记入导入记录
+fn record_written(journal: &Mutex<Journal>, path: &Path, created: bool) {
+    let hash = match scheduler::hash_file(path) {
+        Ok(hash) => hash.to_hex().to_string(),
+        Err(e) => {
+            eprintln!("读取失败 {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let path = path.to_path_buf();
+    let entry = if created {
+        JournalEntry::Created { path, hash }
+    } else {
+        JournalEntry::Updated { path, hash }
+    };
+    if let Err(e) = journal.lock().unwrap().append(&entry) {
+        eprintln!("写入导入记录失败: {e}");
+    }
+}
+
+/// 标记近似照片；`collapse` 时每组连拍只保留最早的一张，其余写入报告但不导入。
+///
+/// 同一目录下主干相同的文件（RAW+JPEG）视为同一张照片。源文件不会被删除。
+fn flag_near_duplicates(
+    mut infos: Vec<ImageInfo>,
+    max_distance: u32,
+    collapse: bool,
+) -> (Vec<ImageInfo>, Vec<ReportRow>) {
+    let mut index = HashMap::<(PathBuf, String), usize>::new();
+    let mut shots = Vec::<(NaiveDateTime, Option<u64>)>::new();
+    let mut shot_paths = Vec::<PathBuf>::new();
+    let mut shot_of = Vec::with_capacity(infos.len());
+    for info in &infos {
+        let shot = *index
+            .entry(rename::group_key(&info.path))
+            .or_insert_with(|| {
+                shots.push((info.date, None));
+                shot_paths.push(info.path.clone());
+                shots.len() - 1
+            });
+        shots[shot].0 = shots[shot].0.min(info.date);
+        shots[shot].1 = shots[shot].1.or(info.phash);
+        shot_of.push(shot);
+    }
+
+    let hashes = shots.iter().map(|shot| shot.1).collect::<Vec<_>>();
+    let groups = similar::find_similar(&hashes, max_distance);
+    for (info, shot) in infos.iter_mut().zip(&shot_of) {
+        info.similar_group = groups[*shot].map(|rep| shot_paths[rep].clone());
+        if let Some(group) = &info.similar_group
+            && *group != info.path
+        {
+            println!("近似：{} ≈ {}", info.path.display(), group.display());
+        }
+    }
+    let group_count = groups.iter().flatten().collect::<HashSet<_>>().len();
+    println!("发现 {group_count} 组近似照片");
+    if !collapse {
+        return (infos, Vec::new());
+    }
+
+    let bursts = similar::find_bursts(&shots, max_distance);
+    let mut kept = Vec::new();
+    let mut rows = Vec::new();
+    for (info, shot) in infos.into_iter().zip(shot_of) {
+        match bursts[shot] {
+            Some(rep) if rep != shot => rows.push(ReportRow {
+                source: info.path.clone(),
+                destination: None,
+                date: Some(info.date),
+                location: info.location(),
+                similar_group: Some(shot_paths[rep].clone()),
+                status: "连拍，已折叠".to_string(),
+            }),
+            _ => kept.push(info),
+        }
+    }
+    println!("折叠连拍 {} 个文件，不会导入", rows.len());
+    (kept, rows)
+}
+
+// 要导入的文件及其目标文件名：照片及其 XMP 附属文件、实况视频，它们跟随照片的目录和新文件名
+fn import_files(images: &[ImageInfo]) -> Vec<(PathBuf, String, &ImageInfo)> {
+    let mut claimed_sidecars = HashSet::<PathBuf>::new();
+    let mut files = Vec::<(PathBuf, String, &ImageInfo)>::new();
+    for image in images {
+        let path = image.path.as_path();
+        let stem = image.new_stem.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        });
+        let file_name = match &image.new_stem {
+            Some(new_stem) => rename::renamed_file_name(path, new_stem),
+            None => path.file_name().unwrap().to_string_lossy().to_string(),
+        };
+        files.push((path.to_path_buf(), file_name, image));
+        for (sidecar, suffix) in rename::find_sidecars(path) {
+            if claimed_sidecars.insert(sidecar.clone()) {
+                files.push((sidecar, format!("{stem}{suffix}"), image));
+            }
+        }
+        if let Some(video) = &image.live_video {
+            let file_name = match &image.new_stem {
+                Some(new_stem) => rename::renamed_file_name(video, new_stem),
+                None => video.file_name().unwrap().to_string_lossy().to_string(),
             };
-            let path = image.path.as_path();
-            // 获取拍摄时间
-            let date_time = match get_date_taken(path) {
-                Ok(dt) => dt,
-                Err(e) => {
-                    println!(
-                        "{} 跳过 {}, 无法获取拍摄时间：{}",
-                        get_idx_str(),
-                        path.to_string_lossy(),
-                        e
-                    );
-                    return;
-                }
+            files.push((video.clone(), file_name, image));
+        }
+    }
+    files
+}
+
+// 导入前检查目标空间是否足够，以及目标是否与来源位于同一设备
+fn check_destinations(
+    infos: &[ImageInfo],
+    destinations: &[Destination],
+    source_is_local: bool,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    // 目标中已存在的文件导入时会跳过，不占用空间
+    let files = import_files(infos);
+    let required = destinations
+        .iter()
+        .map(|destination| {
+            let required = files
+                .iter()
+                .filter(|(_, file_name, image)| {
+                    !destination.dir_for(image).join(file_name).exists()
+                })
+                .filter_map(|(path, _, _)| fs::metadata(path).ok())
+                .map(|metadata| metadata.len())
+                .sum::<u64>();
+            (destination.root.as_path(), required)
+        })
+        .collect::<Vec<_>>();
+    for (root, required) in &required {
+        println!("{} 共需 {}", root.display(), space::format_bytes(*required));
+    }
+
+    let roots = destinations
+        .iter()
+        .map(|d| d.root.as_path())
+        .collect::<Vec<_>>();
+    if source_is_local {
+        let source_devices = infos
+            .iter()
+            .filter_map(|info| space::device(&info.path).ok())
+            .collect::<HashSet<_>>();
+        for root in &roots {
+            if space::device(root).is_ok_and(|dev| source_devices.contains(&dev)) {
+                eprintln!(
+                    "警告：目标 {} 与来源位于同一设备，源设备损坏时备份也会丢失",
+                    root.display()
+                );
+            }
+        }
+    }
+
+    let short = space::check_free_space(&required)?;
+    for (root, required, available) in &short {
+        eprintln!(
+            "空间不足：{} 需要 {}，可用 {}",
+            root.display(),
+            space::format_bytes(*required),
+            space::format_bytes(*available)
+        );
+    }
+    match short.is_empty() || force {
+        true => Ok(()),
+        false => Err("目标空间不足，如仍要导入请使用 --force".into()),
+    }
+}
+
+// 非本地来源的文件从 `staging` 复制，导入记录中写入它们在来源中的路径
+fn do_import(
+    images: &[ImageInfo],
+    destinations: &[Destination],
+    options: &ImportOptions,
+    staging: &Staging,
+    mut journals: Vec<Journal>,
+) -> Vec<ReportRow> {
+    let files = import_files(images);
+    let total_count_str = (files.len() * destinations.len()).to_string();
+    let left_adjust = total_count_str.len();
+    let counter = AtomicUsize::new(0);
+    let get_idx_str = || {
+        let idx = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        format!("[{:left_adjust$}/{}] ", idx + 1, total_count_str)
+    };
+    let mut rows = Vec::<ReportRow>::new();
+    let mut jobs = Vec::<CopyJob>::new();
+    let mut pending = HashMap::<PathBuf, (usize, usize, ReportRow)>::new();
+    for (file_idx, (path, file_name, image)) in files.iter().enumerate() {
+        let path = path.as_path();
+        let mut job = CopyJob {
+            source: path.to_path_buf(),
+            destinations: Vec::new(),
+        };
+        for (dest_idx, destination) in destinations.iter().enumerate() {
+            let mut row = ReportRow {
+                source: path.to_path_buf(),
+                destination: None,
+                date: Some(image.date),
+                location: image.location(),
+                similar_group: image.similar_group.clone(),
+                status: String::new(),
             };
 
             // 构建目标路径
-            let dest_dir = dst_path
-                .join(date_time.format("%Y").to_string())
-                .join(date_time.format("%Y-%m-%d").to_string());
+            let dest_dir = destination.dir_for(image);
 
             // 创建目标目录
-            if let Err(e) = fs::create_dir_all(&dest_dir) {
+            if let Err(e) = journals[dest_idx].create_dir_all(&dest_dir) {
                 eprintln!(
                     "{} 创建目录失败 {}: {}",
                     get_idx_str(),
                     dest_dir.display(),
                     e
                 );
-                return;
+                row.status = format!("创建目录失败: {e}");
+                rows.push(row);
+                continue;
             }
 
             // 处理文件名冲突
-            let file_name = path.file_name().unwrap();
             let dest_path = dest_dir.join(file_name);
-            if dest_path.exists() {
+            row.destination = Some(dest_path.clone());
+            if dest_path.exists() || pending.contains_key(&dest_path) {
                 println!(
                     "{} 文件已存在，跳过: {}",
                     get_idx_str(),
                     dest_path.display()
                 );
-                return;
+                row.status = "文件已存在".to_string();
+                rows.push(row);
+                continue;
             }
+            pending.insert(dest_path.clone(), (dest_idx, file_idx, row));
+            job.destinations.push(dest_path);
+        }
+        if !job.destinations.is_empty() {
+            jobs.push(job);
+        }
+    }
 
-            // 复制文件
-            if let Err(e) = fs::copy(path, &dest_path) {
+    // 复制文件，每个源文件只读取一次
+    let journals = journals.into_iter().map(Mutex::new).collect::<Vec<_>>();
+    let pending = Mutex::new(pending);
+    let done = Mutex::new(rows);
+    let written = Mutex::new(Vec::<(usize, usize, PathBuf)>::new());
+    let stats = scheduler::run(&jobs, &options.io, |job, index, result| {
+        let destination = &job.destinations[index];
+        let (dest_idx, file_idx, mut row) = pending.lock().unwrap().remove(destination).unwrap();
+        match result {
+            Err(e) => {
                 eprintln!(
                     "{} 复制失败 {} -> {}: {}",
                     get_idx_str(),
-                    path.display(),
-                    dest_path.display(),
+                    job.source.display(),
+                    destination.display(),
                     e
                 );
+                row.status = format!("复制失败: {e}");
+            }
+            Ok(copied) => {
+                let method = match copied.method {
+                    CopyMethod::Reflink => "（reflink）",
+                    CopyMethod::CopyFileRange => "（copy_file_range）",
+                    CopyMethod::Buffered => "",
+                };
+                println!(
+                    "{} 已整理{}: {}",
+                    get_idx_str(),
+                    method,
+                    destination.display()
+                );
+                row.status = "已复制".to_string();
+                let entry = JournalEntry::Copied {
+                    source: staging.origin(&job.source),
+                    destination: destination.clone(),
+                    hash: copied.hash,
+                };
+                if let Err(e) = journals[dest_idx].lock().unwrap().append(&entry) {
+                    eprintln!("写入导入记录失败 {}: {}", destination.display(), e);
+                }
+                written
+                    .lock()
+                    .unwrap()
+                    .push((file_idx, dest_idx, destination.clone()));
+            }
+        }
+        done.lock().unwrap().push(row);
+    });
+    let mut rows = done.into_inner().unwrap();
+    let written = written.into_inner().unwrap();
+    let mut copied_media = written
+        .iter()
+        .filter(|(file_idx, _, _)| files[*file_idx].0 == files[*file_idx].2.path)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // 按规则为 HEIC、RAW 额外生成 JPEG，生成的 JPEG 也写入 XMP
+    // 相机同时拍了 JPEG 时（RAW+JPEG）不再生成，否则会与相机的 JPEG 重名
+    let jpeg_stems = images
+        .iter()
+        .filter(|image| thumbnail::is_jpeg(&image.path))
+        .map(|image| rename::group_key(&image.path))
+        .collect::<HashSet<_>>();
+    let mut converted = Vec::new();
+    for (file_idx, dest_idx, destination) in &copied_media {
+        let image = files[*file_idx].2;
+        for rule in options
+            .convert
+            .iter()
+            .filter(|r| r.kind.matches(destination))
+        {
+            if jpeg_stems.contains(&rename::group_key(&image.path)) {
+                println!("已有同名 JPEG，不转换: {}", image.path.display());
+                continue;
+            }
+            let dir = match &rule.layout {
+                Some(layout) => destinations[*dest_idx]
+                    .root
+                    .join(layout.render(&image.date, &image.layout_placeholders())),
+                None => destination.parent().unwrap_or(Path::new("")).to_path_buf(),
+            };
+            let stem = destination
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let target = dir.join(format!("{stem}.jpg"));
+            let mut row = skipped_row(&image.path, Some(image.date), String::new());
+            row.destination = Some(target.clone());
+            row.location = image.location();
+            if target.exists() {
+                row.status = "文件已存在".to_string();
+            } else if let Err(e) = journals[*dest_idx].lock().unwrap().create_dir_all(&dir) {
+                row.status = format!("创建目录失败: {e}");
             } else {
-                println!("{} 已整理: {}", get_idx_str(), dest_path.display());
+                match convert::convert(rule.kind, destination, &target) {
+                    Ok(()) => {
+                        println!("已转换: {}", target.display());
+                        record_written(&journals[*dest_idx], &target, true);
+                        row.status = "已转换".to_string();
+                        converted.push((*file_idx, *dest_idx, target));
+                    }
+                    Err(e) => {
+                        eprintln!("转换失败 {}: {}", destination.display(), e);
+                        row.status = format!("转换失败: {e}");
+                    }
+                }
             }
-        });
+            rows.push(row);
+        }
     }
+    copied_media.extend(converted.iter().cloned());
 
-    pool.join();
+    // 复制全部完成后再写 XMP，避免与复制中的附属文件冲突
+    // 只给了标签时默认写入 JPEG 本身
+    let xmp_mode = match options.tags.is_empty() {
+        true => options.xmp,
+        false => options.xmp.or(Some(XmpMode::Embed)),
+    };
+    if let Some(mode) = xmp_mode {
+        let tag_values = tag_xmp_values(&options.tags);
+        for (file_idx, dest_idx, destination) in copied_media {
+            let mut values = match options.xmp {
+                Some(_) => {
+                    let image = files[file_idx].2;
+                    import_xmp_values(image, &staging.origin(&image.path), options)
+                }
+                None => Vec::new(),
+            };
+            values.extend(
+                tag_values
+                    .iter()
+                    .map(|(tag, value)| (tag.clone(), value.clone())),
+            );
+            match xmp::write(mode, &destination, &values) {
+                Ok((target, created)) => record_written(&journals[dest_idx], &target, created),
+                Err(e) => eprintln!("写入 XMP 失败 {}: {}", destination.display(), e),
+            }
+        }
+    }
+
+    // 文件内容不再改变后才设置属性和时间，缩略图中记录的修改时间才与文件一致
+    for (file_idx, dest_idx, destination) in &written {
+        let (source, _, image) = &files[*file_idx];
+        if let Err(e) = attrs::apply(&options.preserve, source, destination, image.date) {
+            eprintln!("设置文件属性失败 {}: {}", destination.display(), e);
+        }
+        match options
+            .thumbnails
+            .map(|mode| thumbnail::generate(mode, destination))
+        {
+            // 只记录目标目录中的缩略图，系统缓存中的不管
+            Some(Ok(Some(thumb))) if options.thumbnails == Some(ThumbnailMode::Folder) => {
+                record_written(&journals[*dest_idx], &thumb, true);
+            }
+            Some(Err(e)) => {
+                eprintln!("生成缩略图失败 {}: {}", destination.display(), e);
+            }
+            _ => {}
+        }
+    }
+    for (file_idx, _, target) in &converted {
+        let image = files[*file_idx].2;
+        if let Some(times) = options.preserve.times
+            && let Err(e) = attrs::apply_times(times, &image.path, target, image.date)
+        {
+            eprintln!("设置文件时间失败 {}: {}", target.display(), e);
+        }
+    }
+
+    // 汇总
+    for destination in destinations {
+        let of_dest = rows.iter().filter(|row| {
+            row.destination
+                .as_ref()
+                .is_some_and(|d| d.starts_with(&destination.root))
+        });
+        let (mut copied, mut converted, mut skipped, mut failed) = (0, 0, 0, 0);
+        for row in of_dest {
+            match row.status.as_str() {
+                "已复制" => copied += 1,
+                "已转换" => converted += 1,
+                "文件已存在" => skipped += 1,
+                _ => failed += 1,
+            }
+        }
+        println!(
+            "{}：已复制 {}，已转换 {}，已存在 {}，失败 {}",
+            destination.root.display(),
+            copied,
+            converted,
+            skipped,
+            failed
+        );
+    }
+    println!(
+        "共读取 {:.1} MiB，用时 {:.1} 秒，平均 {:.1} MiB/s",
+        stats.bytes as f64 / 1024.0 / 1024.0,
+        stats.elapsed.as_secs_f64(),
+        stats.mib_per_sec()
+    );
+    rows
 }
 
-const USAGE_HINT: &'static str = r#"
+const USAGE_HINT: &str = r#"
 Usage: photo_importer <to> <from>
+    <from> is a directory, a .zip/.tar/.tar.gz archive (e.g. Google Takeout, whose JSON photoTakenTime is used when EXIF has no date), or gphoto2:[port] for phones and cameras over PTP/MTP (needs the gphoto2 command), e.g. gphoto2:usb:001,004
+    Live Photo clips (.MOV) are paired with their HEIC/JPEG by ContentIdentifier or file name and imported next to them.
+       photo_importer reorganize <dir> [--layout <layout>] [--dry-run]
+       photo_importer undo <dir> [<journal>]
+       photo_importer skiplist [list [<volume>] | clear <volume>]
+Common options:
+    [--yes], [--no-confirm]: answer yes to every question, for cron, systemd and scripts.
+        without it, a closed or non-terminal stdin makes prompts fail instead of waiting.
 Options:
     [--time-from]: time from. unix epoch will filled if not given.
-    [--time-to]: time to. a far future time will filled if not given.
+    [--time-to]: time to, inclusive for dates/months/weeks and exact times. a far future time will filled if not given.
+    [--since]: same as --time-from, usually relative like 3d, 12h, 2w.
+    [--date]: both ends from one expression, e.g. 2024-05, 2024-W18, yesterday. an exact time such as 2024-05-01T14:30 covers that whole minute (or second).
+    [--last-import]: start from the time of the last import into <to>.
+    [--layout]: destination folder template, strftime plus {city}, {country} and {event}. default: %Y/%Y-%m-%d
+    [--cluster-gap]: group photos into events, a gap longer than this (e.g. 2h) starts a new event. default layout becomes %Y/{event}
+    [--cluster-distance]: also start a new event when consecutive photos are more than this many km apart.
+    [--geonames]: GeoNames cities file for offline GPS lookup. default: ~/.local/share/photo_importer/cities1000.txt
+    [--report]: write a CSV report of the import to this file.
+    [--also-to]: additional destination, repeatable. use <dir>=<layout> to give it its own layout (only split when <layout> contains % or {).
+    [--rename]: rename files using EXIF, strftime plus {model}, {num}, {name} and {subsec}. "default" means %Y%m%d_%H%M%S_{model}_{num}
+    [--thumbnails]: build thumbnails while importing. freedesktop (~/.cache/thumbnails) or folder (.thumbs/ next to the photos)
+    [--xmp]: record import metadata (original name, source volume, import time, version, time correction). sidecar or embed (JPEG only)
+    [--time-shift]: correct the camera clock, e.g. +1h or -30m. applied before filtering and recorded in XMP
+    [--profile]: apply tag defaults from [profiles.<name>] in the config file.
+        without it, a profile whose volumes = ["<uuid or label>"] lists the source card is applied automatically (for gphoto2 sources: the serial number or model from gphoto2 --summary).
+    [--config]: config file. default: ~/.config/photo_importer/config.toml
+    [--keywords]: comma separated keywords written to XMP/IPTC, e.g. beijing,family
+    [--tag]: one keyword, repeatable.
+    [--copyright], [--artist], [--rating]: copyright notice, creator and rating (0-5). written into JPEGs, or sidecars with --xmp sidecar
+    [--find-similar]: flag near-duplicates (same shot from phone and camera, bursts) by perceptual hash. shown in the report, nothing is deleted
+    [--collapse-bursts]: import only the first frame of each burst, the rest are listed in the report.
+    [--similar-distance]: max hamming distance of 64-bit dHash for near-duplicates. default: 10
+    [--review]: review the plan in a terminal UI before copying: toggle days, events or files, show EXIF and skip reasons.
+    [--exclude]: glob to skip, repeatable. a pattern without / matches any file or folder name, e.g. "._*", "DCIM/999*"
+        .photoimportignore files in the source hold more patterns, relative to their folder.
+    [--no-default-excludes]: also scan .Trashes, .thumbnails, MISC, ._* AppleDouble files, $RECYCLE.BIN and __MACOSX.
+    [--max-depth]: how deep to descend into the source folder.
+    [--follow-symlinks]: follow symbolic links while scanning.
+    [--times]: file times of the copies. source keeps the source atime/mtime (for archives and gphoto2: the mtime recorded there), exif sets mtime to the capture time. default: time of copying
+    [--preserve-permissions]: copy the permission bits of the source files. local directories only
+    [--preserve-xattrs]: copy extended attributes where the destination filesystem supports them. local directories only
+    [--convert]: also write a JPEG next to each HEIC (heic, needs heif-convert from libheif) or a JPEG proxy from the embedded preview of each RAW (raw). repeatable.
+        use <type>=<layout> to place the JPEGs elsewhere under <to>, e.g. raw=proxies/%Y/%Y-%m-%d. EXIF is copied into the JPEGs.
+        files that come with a camera JPEG of the same name (RAW+JPEG) are not converted.
+    [--force]: import even if a destination does not have enough free space.
+    [--metadata]: metadata backend. exiv2 (gexiv2, with exiftool as fallback) or native (pure Rust: JPEG, TIFF, DNG, NEF, RW2, HEIC, MP4/MOV; read only).
+        default: exiv2 when built with it. --xmp, tags and --convert need exiv2.
+    [--readers]: reader threads per source device while copying. default: 1
+    [--writers]: writer threads. default: 2
+Reorganize:
+    move files of an existing destination into the folders given by --layout (strftime only), default: %Y/%Y-%m-%d.
+    moves are logged under <dir>/.photo_importer/reorganize. --dry-run only prints them.
+Undo:
+    revert an import (default: the last one into <dir>) or a reorganize given by its journal file.
+    files changed since the import are kept. the journal is renamed to *.undone afterwards.
+Skip list:
+    files declined in --review, and files deleted from <to> after being imported (asked first and never under --yes, moved files are not deleted ones),
+    are remembered per source volume by content hash and skipped next time. kept in ~/.local/share/photo_importer/skip. clear forgets them, deleted files are not learned again.
+Time expressions:
+    2024-05-01, 2024-05-01T14:30:00, 2024-05, 2024-W18, 2024, today, yesterday, 30m, 12h, 3d, 2w
 "#;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // 让 exiv2 闭嘴。
-    rexiv2::set_log_level(LogLevel::MUTE);
+fn get_scan_options(
+    args: &[String],
+    named_args: &HashMap<&str, &str>,
+) -> Result<ScanOptions, Box<dyn Error>> {
+    let mut patterns = get_repeated_args(args, "--exclude");
+    if !named_args.contains_key("--no-default-excludes") {
+        patterns.extend(exclude::DEFAULT_EXCLUDES);
+    }
+    Ok(ScanOptions {
+        excludes: Excludes::new(&patterns)?,
+        max_depth: named_args
+            .get("--max-depth")
+            .map(|depth| depth.parse())
+            .transpose()?,
+        follow_links: named_args.contains_key("--follow-symlinks"),
+    })
+}
+
+// 配置中的 profile 作为默认值，命令行给出的值优先
+// 未给 --profile 时，使用绑定到来源卷的 profile
+fn get_tag_options(
+    args: &[String],
+    named_args: &HashMap<&str, &str>,
+    volume: Option<&VolumeInfo>,
+) -> Result<TagOptions, Box<dyn Error>> {
+    let mut tags = TagOptions::default();
+    let config_path = named_args
+        .get("--config")
+        .map(PathBuf::from)
+        .or_else(profile::default_config_path);
+    if let Some(name) = named_args.get("--profile") {
+        let config_path = config_path.ok_or("无法确定配置文件位置")?;
+        tags = Config::load(&config_path)?.profile(name)?.tags.clone();
+    } else if let (Some(volume), Some(config_path)) = (volume, config_path) {
+        let config = Config::load(&config_path)?;
+        if let Some((name, profile)) = config.profile_for_volume(volume) {
+            println!("此存储卡绑定了 profile {name}");
+            tags = profile.tags.clone();
+        }
+    }
+    let mut keywords = Vec::new();
+    if let Some(list) = named_args.get("--keywords") {
+        keywords.extend(list.split(',').map(str::trim).filter(|k| !k.is_empty()));
+    }
+    keywords.extend(get_repeated_args(args, "--tag"));
+    tags.merge(TagOptions {
+        keywords: keywords.into_iter().map(str::to_string).collect(),
+        copyright: named_args.get("--copyright").map(|s| s.to_string()),
+        artist: named_args.get("--artist").map(|s| s.to_string()),
+        rating: named_args
+            .get("--rating")
+            .map(|rating| rating.parse::<u8>())
+            .transpose()?,
+    });
+    if tags.rating.is_some_and(|rating| rating > 5) {
+        return Err("评分应在 0-5 之间".into());
+    }
+    Ok(tags)
+}
+
+// 按拍摄时间把已有目录中的文件移动到正确的位置
+fn reorganize_command(
+    positional_args: &[&str],
+    named_args: &HashMap<&str, &str>,
+) -> Result<(), Box<dyn Error>> {
+    if positional_args.len() != 2 {
+        return Err(USAGE_HINT.into());
+    }
+    let root = Path::new(positional_args[1]);
+    let layout = Layout::new(
+        named_args
+            .get("--layout")
+            .unwrap_or(&layout::DEFAULT_LAYOUT),
+    )?;
+    // 整理时只读取拍摄时间，占位符都会变成“未知”
+    if layout.has_placeholders() {
+        return Err(
+            "整理只支持 strftime 格式，--layout 中不能使用 {city}、{event} 等占位符".into(),
+        );
+    }
+    println!("开始扫描: {root:?}");
+    let moves = reorganize::plan(root, &layout, |path| match get_date_taken(path) {
+        Ok(date) => Some(date),
+        Err(e) => {
+            println!("跳过 {}, 无法获取拍摄时间：{}", path.display(), e);
+            None
+        }
+    });
+    if moves.is_empty() {
+        println!("所有文件都已在正确的位置。");
+        return Ok(());
+    }
+    if named_args.contains_key("--dry-run") {
+        for m in &moves {
+            println!("将移动: {} -> {}", m.from.display(), m.to.display());
+        }
+        println!("共 {} 个文件需要移动（未实际移动）", moves.len());
+        return Ok(());
+    }
+    let question = format!("将移动 {} 个文件，是否继续？", moves.len());
+    if !ask_if_continue(&question, true, assume_yes(named_args))? {
+        println!("已取消");
+        return Ok(());
+    }
+    let now = Local::now().naive_local();
+    let mut journal = Journal::create_in(&journal::reorganize_dir(root), now, root)?;
+    println!("撤销记录：{}", journal.path().display());
+    let (moved, failed) = reorganize::apply(root, &moves, &mut journal);
+    println!("已移动 {moved}，失败 {failed}");
+    Ok(())
+}
+
+// 按导入记录撤销一次导入或整理
+fn undo_command(
+    positional_args: &[&str],
+    named_args: &HashMap<&str, &str>,
+) -> Result<(), Box<dyn Error>> {
+    let journal_path = match positional_args {
+        [_, root] => journal::list_journals(Path::new(root))?
+            .pop()
+            .ok_or("没有找到导入记录")?,
+        [_, _, journal] => PathBuf::from(journal),
+        _ => return Err(USAGE_HINT.into()),
+    };
+    let entries = journal::read_journal(&journal_path)?;
+    if let Some(JournalEntry::Started { time, source, .. }) = entries.first() {
+        println!(
+            "导入记录：{}（{}，来源 {}）",
+            journal_path.display(),
+            time,
+            source.display()
+        );
+    }
+    if !ask_if_continue("撤销这次导入？", false, assume_yes(named_args))? {
+        println!("已取消");
+        return Ok(());
+    }
+    let summary = undo::undo(&entries);
+    println!(
+        "已删除 {}，已移回 {}，保留 {}",
+        summary.removed, summary.restored, summary.skipped
+    );
+    // 部分撤销后也不再视为有效的导入，撤销删除的文件不会被当作用户删除的学进跳过列表
+    journal::mark_undone(&journal_path)?;
+    Ok(())
+}
+
+// 查看或清除某个来源卷的跳过列表
+fn skiplist_command(
+    positional_args: &[&str],
+    named_args: &HashMap<&str, &str>,
+) -> Result<(), Box<dyn Error>> {
+    match positional_args {
+        [_] | [_, "list"] => {
+            for volume in skiplist::list_volumes()? {
+                let list = SkipList::load(&volume)?;
+                println!("{}：{} 个文件", volume, list.entries.len());
+            }
+        }
+        [_, "list", volume] => {
+            for (hash, entry) in &SkipList::load(volume)?.entries {
+                println!(
+                    "{}  {}  {}  {}",
+                    &hash[..12.min(hash.len())],
+                    entry.time,
+                    entry.reason,
+                    entry.source.display()
+                );
+            }
+        }
+        [_, "clear", volume] => {
+            let mut list = SkipList::load(volume)?;
+            let question = format!("清除 {} 的 {} 个文件？", volume, list.entries.len());
+            if !ask_if_continue(&question, false, assume_yes(named_args))? {
+                println!("已取消");
+                return Ok(());
+            }
+            list.clear();
+            list.save()?;
+        }
+        _ => return Err(USAGE_HINT.into()),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
     // 解析输入
     let args = std::env::args().collect::<Vec<String>>();
     let named_args = get_named_args(&args);
+    let positional_args = get_positional_args(&args);
+    if let Some(backend) = named_args.get("--metadata") {
+        metadata::init(backend.parse()?);
+    }
+    if positional_args.first() == Some(&"reorganize") {
+        return reorganize_command(&positional_args, &named_args);
+    }
+    if positional_args.first() == Some(&"undo") {
+        return undo_command(&positional_args, &named_args);
+    }
+    if positional_args.first() == Some(&"skiplist") {
+        return skiplist_command(&positional_args, &named_args);
+    }
 
-    assert!(args.len() - named_args.len() * 2 == 3, "{}", USAGE_HINT);
-    let src_path = Path::new(args[2].as_str());
-    let dst_path = Path::new(args[1].as_str());
+    if positional_args.len() != 2 {
+        return Err(USAGE_HINT.into());
+    }
+    let source = source::open(positional_args[1], &get_scan_options(&args, &named_args)?);
+    let dst_path = Path::new(positional_args[0]);
+    // 暂存在目标所在的文件系统中，/tmp 可能是容量很小的 tmpfs
+    let staging = match source.is_local() {
+        true => Staging::new()?,
+        false => Staging::new_in(&journal::staging_dir(dst_path))?,
+    };
 
-    let time_range = get_input_time_range(&named_args).unwrap();
+    let time_range = get_input_time_range(&named_args, dst_path)?;
     println!("时间范围：{:?}", time_range);
-    let scanned = scan_photos(src_path);
+    let mut options = ImportOptions::default();
+    if let Some(readers) = named_args.get("--readers") {
+        options.io.readers = readers.parse()?;
+    }
+    if let Some(writers) = named_args.get("--writers") {
+        options.io.writers = writers.parse()?;
+    }
+    if let Some(mode) = named_args.get("--thumbnails") {
+        options.thumbnails = Some(mode.parse()?);
+    }
+    if let Some(mode) = named_args.get("--xmp") {
+        options.xmp = Some(mode.parse()?);
+        xmp::register_namespace()?;
+    }
+    let volume = source.volume();
+    if let Some(volume) = &volume {
+        println!(
+            "来源卷：{}（UUID {}，卷标 {}）",
+            volume.device,
+            volume.uuid.as_deref().unwrap_or("未知"),
+            volume.label.as_deref().unwrap_or("无")
+        );
+    }
+    let volume_id = source.volume_id();
+    options.tags = get_tag_options(&args, &named_args, volume.as_ref())?;
+    options.convert = get_repeated_args(&args, "--convert")
+        .into_iter()
+        .map(|rule| rule.parse())
+        .collect::<Result<_, _>>()?;
+    options.preserve = PreserveOptions {
+        times: named_args
+            .get("--times")
+            .map(|mode| mode.parse())
+            .transpose()?,
+        permissions: named_args.contains_key("--preserve-permissions"),
+        xattrs: named_args.contains_key("--preserve-xattrs"),
+    };
+    // 压缩包和设备中的文件经临时目录导入，只有修改时间被保留下来
+    if !source.is_local() && (options.preserve.permissions || options.preserve.xattrs) {
+        return Err("--preserve-permissions 和 --preserve-xattrs 只能用于本地目录".into());
+    }
+    // 写入元数据只能通过 exiv2
+    if !cfg!(feature = "exiv2") && (!options.tags.is_empty() || !options.convert.is_empty()) {
+        return Err("标签和 --convert 需要编译时启用 exiv2 功能".into());
+    }
+    options.import_time = Local::now().naive_local();
+    options.source_volume = source.volume_label();
+    let mut shift = Duration::zero();
+    if let Some(input) = named_args.get("--time-shift") {
+        shift = time_range::parse_signed_duration(input).ok_or("无法识别的 --time-shift")?;
+        options.time_shift = Some(input.to_string());
+    }
+
+    // 只有默认的城市文件可以不存在，用户给出的文件不存在时不能静默地把地点都当作未知
+    let geonames = match named_args.get("--geonames") {
+        Some(path) if !Path::new(path).exists() => {
+            return Err(format!("找不到 GeoNames 城市文件：{path}").into());
+        }
+        Some(path) => Some(PathBuf::from(path)),
+        None => geo::default_cities_path().filter(|path| path.exists()),
+    };
+
+    let mut skip_list = SkipList::load(&volume_id)?;
+    // 文件也可能是被手动移走的，经用户确认后才加入跳过列表
+    let deleted = skiplist::deleted_after_import(dst_path, &volume_id)?
+        .into_iter()
+        .filter(|(hash, _, _)| skip_list.can_learn(hash))
+        .collect::<Vec<_>>();
+    if !deleted.is_empty() {
+        for (_, source, _) in &deleted {
+            println!("导入后不见了: {}", source.display());
+        }
+        let question = format!(
+            "{} 个导入过的文件已不在 {} 中，是删除的吗？以后不再导入它们",
+            deleted.len(),
+            dst_path.display()
+        );
+        // 无人值守时不替用户回答，留到下次交互运行时再问
+        if assume_yes(&named_args) {
+            println!("{question}[y/N]: 暂不处理（--yes 时不自动确认）");
+        } else {
+            let confirmed = ask_if_continue(&question, false, false)?;
+            for (hash, source, time) in deleted {
+                if confirmed {
+                    let reason = skiplist::REASON_DELETED.to_string();
+                    skip_list.learn(
+                        hash,
+                        SkipEntry {
+                            source,
+                            reason,
+                            time,
+                        },
+                    );
+                } else {
+                    skip_list.allow(hash);
+                }
+            }
+            skip_list.save()?;
+        }
+    }
+
+    let (scanned, fetch_failed) = scan_photos(source.as_ref(), &staging);
     if scanned.is_empty() {
         println!("未找到图片。");
         return Ok(());
     }
+    let find_similar = named_args.contains_key("--find-similar");
+    let collapse_bursts = named_args.contains_key("--collapse-bursts");
+    let with_phash = find_similar || collapse_bursts;
+    let probed = match source.is_local() {
+        true => None,
+        false => {
+            println!("共 {} 个文件，开始逐个读取图像基本信息", scanned.len());
+            Some(probe_images(
+                source.as_ref(),
+                &staging,
+                shift,
+                with_phash,
+                &time_range,
+                &skip_list,
+            ))
+        }
+    };
+    // 实况照片的视频跟随静态图导入，不单独读取时间和过滤
+    let live_pairs = live::pair(&scanned, |path| match &probed {
+        Some((_, _, identifiers)) => identifiers.get(path).cloned(),
+        None => metadata::reader().content_identifier(path),
+    });
+    let live_videos = live_pairs.values().collect::<HashSet<_>>();
+    if !live_pairs.is_empty() {
+        println!("{} 张实况照片，视频将随照片导入", live_pairs.len());
+    }
+    let (mut infos, mut skipped_rows) = match probed {
+        Some((infos, skipped, _)) => (
+            infos
+                .into_iter()
+                .filter(|info| !live_videos.contains(&info.path))
+                .collect::<Vec<_>>(),
+            skipped
+                .into_iter()
+                .filter(|row| !live_videos.contains(&row.source))
+                .collect::<Vec<_>>(),
+        ),
+        None => {
+            let scanned = scanned
+                .iter()
+                .filter(|path| !live_videos.contains(path))
+                .cloned()
+                .collect::<Vec<_>>();
+            println!("共 {} 张，开始获取图像基本信息", scanned.len());
+            get_image_infos(scanned.as_slice(), shift)
+        }
+    };
+    skipped_rows.extend(fetch_failed);
+    for info in infos.iter_mut() {
+        info.live_video = live_pairs.get(&info.path).cloned();
+    }
+    // 实况视频自身的时间可能不在范围内，读取时没有留下，随照片补取
+    if !source.is_local() {
+        let videos = infos
+            .iter()
+            .filter_map(|info| info.live_video.as_deref())
+            .collect::<HashSet<_>>();
+        let failed = staging.fetch(source.as_ref(), &|path| videos.contains(path))?;
+        let failed_videos = failed.iter().map(|(path, _)| path).collect::<HashSet<_>>();
+        for info in infos.iter_mut() {
+            if info
+                .live_video
+                .as_ref()
+                .is_some_and(|v| failed_videos.contains(v))
+            {
+                info.live_video = None;
+            }
+        }
+        skipped_rows.extend(failed_rows(failed));
+    }
+    let (mut infos, filtered_rows) = filter_images(&infos, &time_range, &skip_list);
+    skipped_rows.extend(filtered_rows);
+    if find_similar || collapse_bursts {
+        // 非本地来源在逐个读取时已计算
+        if source.is_local() {
+            compute_phashes(&mut infos);
+        }
+        let max_distance = match named_args.get("--similar-distance") {
+            Some(distance) => distance.parse()?,
+            None => similar::DEFAULT_MAX_DISTANCE,
+        };
+        let collapsed_rows;
+        (infos, collapsed_rows) = flag_near_duplicates(infos, max_distance, collapse_bursts);
+        skipped_rows.extend(collapsed_rows);
+    }
 
-    println!("共 {} 张，开始获取图像基本信息", scanned.len());
-    let infos = get_image_infos(scanned.as_slice());
-    let infos = filter_images(&infos, &time_range);
+    let cluster_options = match named_args.get("--cluster-gap") {
+        Some(gap) => Some(cluster::ClusterOptions {
+            max_gap: time_range::parse_duration(gap).ok_or("无法识别的 --cluster-gap")?,
+            max_distance_km: named_args
+                .get("--cluster-distance")
+                .map(|km| km.parse::<f64>())
+                .transpose()?,
+        }),
+        None => None,
+    };
+    let default_layout = if cluster_options.is_some() {
+        layout::DEFAULT_EVENT_LAYOUT
+    } else {
+        layout::DEFAULT_LAYOUT
+    };
+    let layout = Layout::new(named_args.get("--layout").unwrap_or(&default_layout))?;
+    let mut destinations = vec![Destination {
+        root: dst_path.to_path_buf(),
+        layout: layout.clone(),
+    }];
+    for also_to in get_repeated_args(&args, "--also-to") {
+        // 目录名本身也可能含有 `=`（如 /mnt/backup=2024），右侧像模板时才拆分
+        let (root, layout) = match also_to.rsplit_once('=') {
+            Some((root, template)) if template.contains(['%', '{']) => {
+                (root, Layout::new(template)?)
+            }
+            _ => (also_to, layout.clone()),
+        };
+        destinations.push(Destination {
+            root: PathBuf::from(root),
+            layout,
+        });
+    }
+    let rename_layout = named_args
+        .get("--rename")
+        .map(|template| match *template {
+            "default" => Layout::new(rename::DEFAULT_RENAME),
+            template => Layout::new(template),
+        })
+        .transpose()?;
+    let need_gps = geonames.is_some()
+        || cluster_options
+            .as_ref()
+            .is_some_and(|options| options.max_distance_km.is_some());
+    if need_gps {
+        for info in infos.iter_mut() {
+            info.gps = get_gps(&info.path);
+        }
+    }
+    if let Some(geonames) = geonames {
+        let db = GeoDatabase::load(&geonames)?;
+        println!("已加载 {} 个城市：{}", db.len(), geonames.display());
+        for info in infos.iter_mut() {
+            info.place = info.gps.and_then(|(lat, lon)| db.lookup(lat, lon));
+        }
+    }
+    if let Some(options) = cluster_options {
+        let items = infos.iter().map(|i| (i.date, i.gps)).collect::<Vec<_>>();
+        let events = cluster::cluster_events(&items, &options);
+        for (info, event) in infos.iter_mut().zip(events) {
+            info.event = Some(event);
+        }
+    }
 
-    // 打印确认消息
+    if named_args.contains_key("--review") {
+        if !io::stdin().is_terminal() {
+            return Err("--review 需要在终端中运行".into());
+        }
+        let Some(selected) = review::run(&infos, &skipped_rows)? else {
+            println!("已取消");
+            return Ok(());
+        };
+        let mut kept = Vec::new();
+        for (info, selected) in infos.into_iter().zip(selected) {
+            if selected {
+                kept.push(info);
+            } else {
+                // 记住拒绝的文件，下次不再提供
+                if let Some(hash) = file_hash(&info.path) {
+                    let entry = SkipEntry {
+                        source: staging.origin(&info.path),
+                        reason: skiplist::REASON_DECLINED.to_string(),
+                        time: options.import_time,
+                    };
+                    skip_list.add(hash, entry);
+                }
+                skipped_rows.push(skipped_row(
+                    &info.path,
+                    Some(info.date),
+                    skiplist::REASON_DECLINED.to_string(),
+                ));
+            }
+        }
+        infos = kept;
+        skip_list.save()?;
+    }
+
+    if let Some(template) = &rename_layout {
+        let camera_infos = infos
+            .iter()
+            .map(|info| get_camera_info(&info.path))
+            .collect::<Vec<_>>();
+        let inputs = infos
+            .iter()
+            .zip(camera_infos)
+            .map(|(info, (model, subsec))| rename::RenameInput {
+                path: &info.path,
+                date: info.date,
+                subsec,
+                model,
+            })
+            .collect::<Vec<_>>();
+        let names = rename::assign_names(&inputs, template);
+        for (info, name) in infos.iter_mut().zip(names) {
+            info.new_stem = Some(name);
+        }
+    }
+
+    check_destinations(
+        &infos,
+        &destinations,
+        source.is_local(),
+        named_args.contains_key("--force"),
+    )?;
+
+    // 打印确认消息，审阅界面中已确认过的不再询问
     let question_continue = format!("找到 {} 张照片（已过滤）, 是否要开始导入？", infos.len());
-    if !ask_if_continue(question_continue.as_str(), true) {
+    if !named_args.contains_key("--review")
+        && !ask_if_continue(question_continue.as_str(), true, assume_yes(&named_args))?
+    {
         println!("已取消");
         return Ok(());
     }
     // 开始导出
-    do_import(infos.as_slice(), dst_path);
+    let mut journals = Vec::new();
+    for destination in &destinations {
+        let journal = Journal::create(
+            &destination.root,
+            options.import_time,
+            Path::new(&source.describe()),
+            &volume_id,
+        )?;
+        println!("导入记录：{}", journal.path().display());
+        journals.push(journal);
+    }
+    let mut rows = skipped_rows;
+    rows.extend(do_import(
+        infos.as_slice(),
+        &destinations,
+        &options,
+        &staging,
+        journals,
+    ));
+    // 报告中写来源中的路径，而不是结束时删除的临时目录
+    for row in rows.iter_mut() {
+        row.source = staging.origin(&row.source);
+        row.similar_group = row.similar_group.as_deref().map(|p| staging.origin(p));
+    }
+    if let Some(report_path) = named_args.get("--report") {
+        report::write_csv(Path::new(report_path), &volume_id, &rows)?;
+        println!("导入报告：{report_path}");
+    }
 
     Ok(())
 }
 
-fn get_date_taken(path: &Path) -> Result<NaiveDateTime, Box<dyn Error>> {
-    // 加载元数据
-    let metadata = Metadata::new_from_path(path)?;
-    let datetime_str = metadata
-        .get_tag_string("Exif.Photo.DateTimeOriginal")
-        .or_else(|_| metadata.get_tag_string("Exif.Photo.DateTime"))
-        .or_else(|_| {
-            let mut tool = ExifTool::new().unwrap();
-            tool.read_tag::<String>(path, "DateTimeOriginal")
-                .or_else(|_| tool.read_tag::<String>(path, "DateTime"))
-        })?;
-    Ok(NaiveDateTime::parse_from_str(&datetime_str, "%Y:%m:%d %H:%M:%S")?.into())
+fn get_date_taken(path: &Path) -> Result<NaiveDateTime, ImportError> {
+    // EXIF 中没有拍摄时间时，使用 Google Takeout 的 JSON 附属文件
+    metadata::reader()
+        .date_taken(path)
+        .or_else(|e| takeout::date_taken(path).ok_or(e))
+}
+
+// (相机型号简称, 亚秒)
+fn get_camera_info(path: &Path) -> (Option<String>, Option<String>) {
+    let camera = metadata::reader().camera(path);
+    let model = camera
+        .model
+        .map(|model| rename::short_model(camera.make.as_deref(), &model))
+        .filter(|model| !model.is_empty());
+    let subsec = camera
+        .subsec
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    (model, subsec)
+}
+
+fn get_gps(path: &Path) -> Option<(f64, f64)> {
+    metadata::reader()
+        .gps(path)
+        .filter(|gps| *gps != (0.0, 0.0))
 }
 
 #[test]
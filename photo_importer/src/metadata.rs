@@ -0,0 +1,135 @@
+#[cfg(feature = "native")]
+mod bmff;
+#[cfg(feature = "exiv2")]
+mod exiv2;
+#[cfg(feature = "native")]
+mod native;
+
+use crate::error::ImportError;
+use chrono::NaiveDateTime;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[cfg(not(any(feature = "exiv2", feature = "native")))]
+compile_error!("至少需要启用 exiv2 或 native 功能之一");
+
+/// 相机信息，用于重命名。
+#[derive(Clone, Debug, Default)]
+pub struct Camera {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub subsec: Option<String>,
+}
+
+/// 读取照片、视频元数据的后端。
+pub trait MetadataReader: Send + Sync {
+    fn date_taken(&self, path: &Path) -> Result<NaiveDateTime, ImportError>;
+
+    fn camera(&self, path: &Path) -> Camera;
+
+    /// (纬度, 经度)
+    fn gps(&self, path: &Path) -> Option<(f64, f64)>;
+
+    /// EXIF 方向，1-8
+    fn orientation(&self, path: &Path) -> Option<u16>;
+
+    /// 审阅界面中显示的标签：(标题, 值)
+    fn summary(&self, path: &Path) -> Result<Vec<(String, String)>, ImportError>;
+
+    /// RAW 中最大的内嵌预览
+    fn preview(&self, _path: &Path) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// 实况照片的 ContentIdentifier
+    fn content_identifier(&self, _path: &Path) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    // gexiv2，可选 exiftool 作为后备
+    #[cfg(feature = "exiv2")]
+    Exiv2,
+    // 纯 Rust 解析，不依赖系统库
+    #[cfg(feature = "native")]
+    Native,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        #[cfg(feature = "exiv2")]
+        return Backend::Exiv2;
+        #[cfg(not(feature = "exiv2"))]
+        return Backend::Native;
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "exiv2")]
+            "exiv2" => Ok(Backend::Exiv2),
+            #[cfg(feature = "native")]
+            "native" => Ok(Backend::Native),
+            // 编译时未启用的后端
+            _ if s == "exiv2" || s == "native" => Err(format!("编译时未启用 {s} 功能")),
+            _ => Err(format!("未知的元数据后端：{s}，可选 exiv2、native")),
+        }
+    }
+}
+
+static READER: OnceLock<Box<dyn MetadataReader>> = OnceLock::new();
+
+fn create(backend: Backend) -> Box<dyn MetadataReader> {
+    match backend {
+        #[cfg(feature = "exiv2")]
+        Backend::Exiv2 => Box::new(exiv2::Exiv2Reader::new()),
+        #[cfg(feature = "native")]
+        Backend::Native => Box::new(native::NativeReader),
+    }
+}
+
+/// 选择后端，须在第一次调用 `reader` 之前。
+pub fn init(backend: Backend) {
+    let _ = READER.set(create(backend));
+}
+
+pub fn reader() -> &'static dyn MetadataReader {
+    READER.get_or_init(|| create(Backend::default())).as_ref()
+}
+
+/// 解析 EXIF 格式的时间 `2024:05:01 14:30:00`，忽略末尾的时区，如 `+08:00`。
+pub fn parse_exif_datetime(value: &str) -> Result<NaiveDateTime, ImportError> {
+    let trimmed = value.trim();
+    let trimmed = trimmed.get(..19).unwrap_or(trimmed);
+    NaiveDateTime::parse_from_str(trimmed, "%Y:%m:%d %H:%M:%S")
+        .map_err(|e| ImportError::Parse(format!("{value}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dt;
+
+    #[test]
+    fn parses_exif_datetime() {
+        let expected = dt("2024-05-01T14:30:00");
+        assert_eq!(
+            parse_exif_datetime("2024:05:01 14:30:00").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_exif_datetime("2024:05:01 14:30:00+08:00").unwrap(),
+            expected
+        );
+        assert!(matches!(
+            parse_exif_datetime("0000:00:00 00:00:00"),
+            Err(ImportError::Parse(_))
+        ));
+    }
+}
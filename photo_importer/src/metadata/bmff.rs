@@ -0,0 +1,231 @@
+use chrono::{DateTime, Local, NaiveDateTime};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// 1904-01-01 到 1970-01-01 的秒数
+const MAC_EPOCH_OFFSET: i64 = 2_082_844_800;
+// moov/meta 中只有少量键值，超过时视为文件损坏
+const MAX_META_LEN: u64 = 16 * 1024 * 1024;
+
+// 读取盒子头，返回 (类型, 盒子总长度, 内容长度)；到末尾时返回 None
+fn read_header(file: &mut impl Read, remaining: u64) -> io::Result<Option<([u8; 4], u64, u64)>> {
+    if remaining < 8 {
+        return Ok(None);
+    }
+    let mut header = [0; 8];
+    file.read_exact(&mut header)?;
+    let size = u32::from_be_bytes(header[..4].try_into().unwrap()) as u64;
+    let kind = header[4..].try_into().unwrap();
+    let (size, header_len) = match size {
+        // 64 位长度
+        1 => {
+            let mut large = [0; 8];
+            file.read_exact(&mut large)?;
+            (u64::from_be_bytes(large), 16)
+        }
+        // 延伸到文件（或父盒子）末尾
+        0 => (remaining, 8),
+        size => (size, 8),
+    };
+    if size < header_len || size > remaining {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "盒子长度无效"));
+    }
+    Ok(Some((kind, size, size - header_len)))
+}
+
+// 在当前层级中找到 `kind` 盒子，返回其内容长度，文件位置停在内容开头
+fn find_box(
+    file: &mut (impl Read + Seek),
+    kind: &[u8; 4],
+    mut remaining: u64,
+) -> io::Result<Option<u64>> {
+    while let Some((found, size, len)) = read_header(file, remaining)? {
+        if &found == kind {
+            return Ok(Some(len));
+        }
+        file.seek(SeekFrom::Current(len as i64))?;
+        remaining -= size;
+    }
+    Ok(None)
+}
+
+fn creation_time_from(
+    file: &mut (impl Read + Seek),
+    len: u64,
+) -> io::Result<Option<NaiveDateTime>> {
+    let Some(moov) = find_box(file, b"moov", len)? else {
+        return Ok(None);
+    };
+    let Some(mvhd) = find_box(file, b"mvhd", moov)? else {
+        return Ok(None);
+    };
+    let mut version = [0; 4];
+    file.read_exact(&mut version)?;
+    let seconds = if version[0] == 1 && mvhd >= 12 {
+        let mut time = [0; 8];
+        file.read_exact(&mut time)?;
+        u64::from_be_bytes(time)
+    } else {
+        let mut time = [0; 4];
+        file.read_exact(&mut time)?;
+        u32::from_be_bytes(time) as u64
+    };
+    // 0 表示未设置
+    if seconds == 0 {
+        return Ok(None);
+    }
+    // mvhd 中为 UTC 时间，与照片一致转为本地时间
+    Ok(
+        DateTime::from_timestamp(seconds as i64 - MAC_EPOCH_OFFSET, 0)
+            .map(|t| t.with_timezone(&Local).naive_local()),
+    )
+}
+
+// 内存中同一层级的盒子：(类型, 内容)
+fn child_boxes(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut ret = Vec::new();
+    while data.len() >= 8 {
+        let size = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        if size < 8 || size > data.len() {
+            break;
+        }
+        ret.push((data[4..8].try_into().unwrap(), &data[8..size]));
+        data = &data[size..];
+    }
+    ret
+}
+
+fn find_child<'a>(boxes: &[([u8; 4], &'a [u8])], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes
+        .iter()
+        .find(|(found, _)| found == kind)
+        .map(|(_, content)| *content)
+}
+
+// QuickTime 元数据：`keys` 中依次列出键名，`ilst` 中盒子的类型为键的序号（从 1 开始）
+fn metadata_value(meta: &[u8], key: &str) -> Option<String> {
+    // QuickTime 的 meta 没有 ISO meta 开头的版本和标志
+    let meta = match meta.get(4..8) {
+        Some(b"hdlr") => meta,
+        _ => meta.get(4..)?,
+    };
+    let children = child_boxes(meta);
+    // keys：版本和标志、数量，之后每项为 长度、命名空间、键名
+    let keys = find_child(&children, b"keys")?;
+    let mut pos = 8;
+    let mut index = 0u32;
+    let found = loop {
+        let size = u32::from_be_bytes(keys.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let name = keys.get(pos + 8..pos.checked_add(size)?)?;
+        index += 1;
+        if size < 8 {
+            return None;
+        }
+        if name == key.as_bytes() {
+            break index;
+        }
+        pos += size;
+    };
+    let items = child_boxes(find_child(&children, b"ilst")?);
+    let item = find_child(&items, &found.to_be_bytes())?;
+    // data：类型、语言，之后为值
+    let value = find_child(&child_boxes(item), b"data")?.get(8..)?;
+    let value = String::from_utf8_lossy(value).trim().to_string();
+    Some(value).filter(|v| !v.is_empty())
+}
+
+fn metadata_from(file: &mut (impl Read + Seek), len: u64, key: &str) -> io::Result<Option<String>> {
+    let Some(moov) = find_box(file, b"moov", len)? else {
+        return Ok(None);
+    };
+    let Some(meta) = find_box(file, b"meta", moov)? else {
+        return Ok(None);
+    };
+    if meta > MAX_META_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "meta 盒子过大"));
+    }
+    let mut data = vec![0; meta as usize];
+    file.read_exact(&mut data)?;
+    Ok(metadata_value(&data, key))
+}
+
+/// MOV 中 `moov/meta` 的 QuickTime 元数据里 `key` 的值，
+/// 如实况照片视频的 `com.apple.quicktime.content.identifier`。
+pub fn quicktime_metadata(path: &Path, key: &str) -> io::Result<Option<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    metadata_from(&mut file, len, key)
+}
+
+/// MP4/MOV 中 `moov/mvhd` 记录的创建时间。
+pub fn creation_time(path: &Path) -> io::Result<Option<NaiveDateTime>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    creation_time_from(&mut file, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_mvhd_creation_time() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&16u32.to_be_bytes());
+        data.extend_from_slice(b"ftypqt  ");
+        data.extend_from_slice(&0u32.to_be_bytes());
+        // moov 包含 mvhd（版本 0，只写到创建时间）
+        data.extend_from_slice(&24u32.to_be_bytes());
+        data.extend_from_slice(b"moov");
+        data.extend_from_slice(&16u32.to_be_bytes());
+        data.extend_from_slice(b"mvhd");
+        data.extend_from_slice(&[0; 4]);
+        let seconds = (1_714_573_800 + MAC_EPOCH_OFFSET) as u32;
+        data.extend_from_slice(&seconds.to_be_bytes());
+
+        let len = data.len() as u64;
+        let time = creation_time_from(&mut Cursor::new(data), len)
+            .unwrap()
+            .unwrap();
+        let expected = DateTime::from_timestamp(1_714_573_800, 0)
+            .unwrap()
+            .with_timezone(&Local)
+            .naive_local();
+        assert_eq!(time, expected);
+    }
+
+    fn boxed(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut data = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(content);
+        data
+    }
+
+    #[test]
+    fn reads_quicktime_keys() {
+        let key = "com.apple.quicktime.content.identifier";
+        let mut keys = vec![0, 0, 0, 0, 0, 0, 0, 2];
+        for name in ["com.apple.quicktime.make", key] {
+            keys.extend_from_slice(&((name.len() + 8) as u32).to_be_bytes());
+            keys.extend_from_slice(b"mdta");
+            keys.extend_from_slice(name.as_bytes());
+        }
+        let value = |text: &str| {
+            let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+            data.extend_from_slice(text.as_bytes());
+            boxed(b"data", &data)
+        };
+        let mut ilst = boxed(&1u32.to_be_bytes(), &value("Apple"));
+        ilst.extend(boxed(&2u32.to_be_bytes(), &value("0C2E6A55-1D2B")));
+        let mut meta = boxed(b"hdlr", &[0; 24]);
+        meta.extend(boxed(b"keys", &keys));
+        meta.extend(boxed(b"ilst", &ilst));
+        let data = boxed(b"moov", &boxed(b"meta", &meta));
+
+        let len = data.len() as u64;
+        let id = metadata_from(&mut Cursor::new(data), len, key).unwrap();
+        assert_eq!(id.as_deref(), Some("0C2E6A55-1D2B"));
+    }
+}
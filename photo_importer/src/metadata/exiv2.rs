@@ -0,0 +1,164 @@
+use super::{Camera, MetadataReader, parse_exif_datetime};
+use crate::error::ImportError;
+use chrono::NaiveDateTime;
+#[cfg(feature = "exiftool")]
+use exiftool::ExifTool;
+use rexiv2::{LogLevel, Metadata};
+use std::fs;
+#[cfg(not(feature = "exiftool"))]
+use std::io;
+use std::path::Path;
+#[cfg(feature = "exiftool")]
+use std::sync::Mutex;
+
+const SUMMARY_TAGS: &[(&str, &str)] = &[
+    ("相机", "Exif.Image.Model"),
+    ("镜头", "Exif.Photo.LensModel"),
+    ("拍摄时间", "Exif.Photo.DateTimeOriginal"),
+    ("快门", "Exif.Photo.ExposureTime"),
+    ("光圈", "Exif.Photo.FNumber"),
+    ("ISO", "Exif.Photo.ISOSpeedRatings"),
+    ("焦距", "Exif.Photo.FocalLength"),
+    ("尺寸", "Exif.Photo.PixelXDimension"),
+];
+
+/// 通过 gexiv2 读取；exiv2 读不到时交给 exiftool（如 iPhone 视频）。
+pub struct Exiv2Reader {
+    // exiftool 常驻进程，首次需要时启动，各线程共用
+    #[cfg(feature = "exiftool")]
+    exiftool: Mutex<Option<ExifTool>>,
+}
+
+impl Exiv2Reader {
+    pub fn new() -> Exiv2Reader {
+        // 让 exiv2 闭嘴。
+        rexiv2::set_log_level(LogLevel::MUTE);
+        Exiv2Reader {
+            #[cfg(feature = "exiftool")]
+            exiftool: Mutex::new(None),
+        }
+    }
+
+    // 依次尝试 `tags`，返回第一个读到的值
+    #[cfg(feature = "exiftool")]
+    fn exiftool_tag(&self, path: &Path, tags: &[&str]) -> Result<String, ImportError> {
+        let mut tool = self.exiftool.lock().unwrap();
+        if tool.is_none() {
+            *tool = Some(ExifTool::new()?);
+        }
+        let tool = tool.as_mut().unwrap();
+        let mut last = ImportError::MetadataUnavailable(tags.join("、"));
+        for tag in tags {
+            match tool.read_tag::<String>(path, tag) {
+                Ok(value) => return Ok(value),
+                Err(e) => last = e.into(),
+            }
+        }
+        Err(last)
+    }
+
+    #[cfg(not(feature = "exiftool"))]
+    fn exiftool_tag(&self, _path: &Path, _tags: &[&str]) -> Result<String, ImportError> {
+        Err(ImportError::ExifToolMissing(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "编译时未启用 exiftool 功能",
+        )))
+    }
+}
+
+impl MetadataReader for Exiv2Reader {
+    fn date_taken(&self, path: &Path) -> Result<NaiveDateTime, ImportError> {
+        // 先确认文件本身可读，以免把读取失败当成格式问题
+        fs::File::open(path)?;
+        let metadata = Metadata::new_from_path(path);
+        let datetime_str = match &metadata {
+            Ok(metadata) => metadata
+                .get_tag_string("Exif.Photo.DateTimeOriginal")
+                .or_else(|_| metadata.get_tag_string("Exif.Photo.DateTime"))
+                .ok(),
+            Err(_) => None,
+        };
+        let datetime_str = match datetime_str {
+            Some(datetime_str) => datetime_str,
+            // iPhone 视频的 CreationDate 为带时区的本地时间
+            None => match (
+                self.exiftool_tag(path, &["DateTimeOriginal", "CreationDate", "DateTime"]),
+                metadata,
+            ) {
+                (Ok(datetime_str), _) => datetime_str,
+                // 两者都读不了时，以 exiv2 的报错为准
+                (
+                    Err(ImportError::UnsupportedFormat(_) | ImportError::ExifToolMissing(_)),
+                    Err(e),
+                ) => return Err(ImportError::UnsupportedFormat(e.to_string())),
+                // 没有 exiftool 时，exiv2 读到了文件但没有时间标签
+                #[cfg(not(feature = "exiftool"))]
+                (Err(ImportError::ExifToolMissing(_)), Ok(_)) => {
+                    return Err(ImportError::MetadataUnavailable(
+                        "Exif.Photo.DateTimeOriginal".to_string(),
+                    ));
+                }
+                (Err(e), _) => return Err(e),
+            },
+        };
+        parse_exif_datetime(&datetime_str)
+    }
+
+    fn camera(&self, path: &Path) -> Camera {
+        let Ok(metadata) = Metadata::new_from_path(path) else {
+            return Camera::default();
+        };
+        Camera {
+            make: metadata.get_tag_string("Exif.Image.Make").ok(),
+            model: metadata.get_tag_string("Exif.Image.Model").ok(),
+            subsec: metadata
+                .get_tag_string("Exif.Photo.SubSecTimeOriginal")
+                .ok(),
+        }
+    }
+
+    fn gps(&self, path: &Path) -> Option<(f64, f64)> {
+        let gps = Metadata::new_from_path(path).ok()?.get_gps_info()?;
+        Some((gps.latitude, gps.longitude))
+    }
+
+    fn orientation(&self, path: &Path) -> Option<u16> {
+        let metadata = Metadata::new_from_path(path).ok()?;
+        Some(metadata.get_orientation() as u16).filter(|o| *o != 0)
+    }
+
+    fn summary(&self, path: &Path) -> Result<Vec<(String, String)>, ImportError> {
+        let metadata = Metadata::new_from_path(path)
+            .map_err(|e| ImportError::UnsupportedFormat(e.to_string()))?;
+        let mut ret = SUMMARY_TAGS
+            .iter()
+            .filter_map(|(label, tag)| {
+                let value = metadata.get_tag_interpreted_string(tag).ok()?;
+                Some((label.to_string(), value))
+            })
+            .collect::<Vec<_>>();
+        if let Some(gps) = metadata.get_gps_info() {
+            ret.push((
+                "GPS".to_string(),
+                format!("{:.5}, {:.5}", gps.latitude, gps.longitude),
+            ));
+        }
+        Ok(ret)
+    }
+
+    fn preview(&self, path: &Path) -> Option<Vec<u8>> {
+        let metadata = Metadata::new_from_path(path).ok()?;
+        let previews = metadata.get_preview_images()?;
+        let largest = previews
+            .iter()
+            .max_by_key(|p| p.get_width() * p.get_height())?;
+        largest.get_data().ok()
+    }
+
+    fn content_identifier(&self, path: &Path) -> Option<String> {
+        // 苹果 MakerNote / QuickTime Keys 中的标签，exiv2 不认识
+        self.exiftool_tag(path, &["ContentIdentifier"])
+            .ok()
+            .filter(|id| !id.is_empty())
+    }
+}
@@ -0,0 +1,297 @@
+use super::{Camera, MetadataReader, bmff, parse_exif_datetime};
+use crate::error::ImportError;
+use chrono::NaiveDateTime;
+use exif::{Exif, In, Reader, Tag, Value};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const SUMMARY_TAGS: &[(&str, Tag)] = &[
+    ("相机", Tag::Model),
+    ("镜头", Tag::LensModel),
+    ("拍摄时间", Tag::DateTimeOriginal),
+    ("快门", Tag::ExposureTime),
+    ("光圈", Tag::FNumber),
+    ("ISO", Tag::PhotographicSensitivity),
+    ("焦距", Tag::FocalLength),
+    ("尺寸", Tag::PixelXDimension),
+];
+
+// 松下 RW2 的 IFD0 中整张内嵌的 JPEG，完整的 EXIF 在其中
+const JPG_FROM_RAW: u16 = 0x002e;
+// 内嵌 JPEG 超过此大小时视为文件损坏
+const MAX_JPG_FROM_RAW: usize = 64 * 1024 * 1024;
+
+// 苹果 MakerNote："Apple iOS\0"、版本号、字节序，之后的 IFD 偏移相对于 MakerNote 开头
+const APPLE_MAKER_NOTE: &[u8] = b"Apple iOS\0";
+const APPLE_CONTENT_IDENTIFIER: u16 = 0x0011;
+// 实况照片视频在 QuickTime 元数据中的 ContentIdentifier
+const QUICKTIME_CONTENT_IDENTIFIER: &str = "com.apple.quicktime.content.identifier";
+
+/// 纯 Rust 解析：JPEG、TIFF 及基于 TIFF 的 RAW（DNG、NEF、RW2）、HEIC 中的 EXIF，
+/// 以及 MP4/MOV 的创建时间和实况照片的 ContentIdentifier。
+pub struct NativeReader;
+
+impl From<exif::Error> for ImportError {
+    fn from(e: exif::Error) -> Self {
+        match e {
+            exif::Error::Io(e) => ImportError::Io(e),
+            exif::Error::InvalidFormat(detail) => {
+                ImportError::UnsupportedFormat(detail.to_string())
+            }
+            e => ImportError::MetadataUnavailable(e.to_string()),
+        }
+    }
+}
+
+enum Rw2 {
+    // IFD0 中内嵌的整张 JPEG
+    Jpeg(Vec<u8>),
+    // 没有内嵌 JPEG，只能读取 RAW 本身的 IFD
+    Raw(Exif),
+}
+
+// RW2 的文件头为 IIU\0，其余结构与 TIFF 相同（小端）。只读取文件头、IFD0 和内嵌的 JPEG
+fn read_rw2(path: &Path) -> Result<Option<Rw2>, ImportError> {
+    let mut file = File::open(path)?;
+    let mut header = [0; 8];
+    if file.read_exact(&mut header).is_err() || &header[..4] != b"IIU\0" {
+        return Ok(None);
+    }
+    let ifd0 = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    file.seek(SeekFrom::Start(ifd0 as u64))?;
+    let mut count = [0; 2];
+    file.read_exact(&mut count)?;
+    let mut entries = vec![0; u16::from_le_bytes(count) as usize * 12];
+    file.read_exact(&mut entries)?;
+    for entry in entries.chunks_exact(12) {
+        if u16::from_le_bytes([entry[0], entry[1]]) != JPG_FROM_RAW {
+            continue;
+        }
+        let len = u32::from_le_bytes(entry[4..8].try_into().unwrap()) as usize;
+        let offset = u32::from_le_bytes(entry[8..12].try_into().unwrap());
+        if len > MAX_JPG_FROM_RAW {
+            break;
+        }
+        let mut jpeg = vec![0; len];
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.read_exact(&mut jpeg)?;
+        return Ok(Some(Rw2::Jpeg(jpeg)));
+    }
+    // 少见：没有内嵌 JPEG 时才读取整个文件
+    let mut data = Vec::new();
+    file.rewind()?;
+    file.read_to_end(&mut data)?;
+    data[2..4].copy_from_slice(&42u16.to_le_bytes());
+    Ok(Some(Rw2::Raw(Reader::new().read_raw(data)?)))
+}
+
+fn read_exif(path: &Path) -> Result<Exif, ImportError> {
+    match read_rw2(path)? {
+        Some(Rw2::Jpeg(jpeg)) => Ok(Reader::new().read_from_container(&mut Cursor::new(jpeg))?),
+        Some(Rw2::Raw(raw)) => Ok(raw),
+        None => {
+            let mut file = BufReader::new(File::open(path)?);
+            Ok(Reader::new().read_from_container(&mut file)?)
+        }
+    }
+}
+
+// MakerNote IFD 中 `tag` 的 ASCII 值
+fn apple_maker_note_ascii(note: &[u8], tag: u16) -> Option<String> {
+    if !note.starts_with(APPLE_MAKER_NOTE) {
+        return None;
+    }
+    let big_endian = match note.get(12..14)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| {
+        let bytes = note.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |pos: usize| {
+        let bytes = note.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        } as usize)
+    };
+    let entry = (0..u16_at(14)? as usize)
+        .map(|i| 16 + i * 12)
+        .find(|entry| u16_at(*entry) == Some(tag))?;
+    // 类型 2 为 ASCII，不超过 4 字节时直接存放在条目中
+    if u16_at(entry + 2)? != 2 {
+        return None;
+    }
+    let len = u32_at(entry + 4)?;
+    let value = if len <= 4 {
+        note.get(entry + 8..entry + 8 + len)?
+    } else {
+        let offset = u32_at(entry + 8)?;
+        note.get(offset..offset.checked_add(len)?)?
+    };
+    let value = String::from_utf8_lossy(value)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+    Some(value).filter(|v| !v.is_empty())
+}
+
+fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let value = String::from_utf8_lossy(values.first()?).trim().to_string();
+            Some(value).filter(|v| !v.is_empty())
+        }
+        _ => None,
+    }
+}
+
+// 度、分、秒三个有理数，南纬、西经为负
+fn coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative: &str) -> Option<f64> {
+    let Value::Rational(values) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let value = values
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(v, unit)| v.to_f64() / unit)
+        .sum::<f64>();
+    if ascii(exif, ref_tag).as_deref() == Some(negative) {
+        Some(-value)
+    } else {
+        Some(value)
+    }
+}
+
+fn gps(exif: &Exif) -> Option<(f64, f64)> {
+    let latitude = coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?;
+    let longitude = coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+    Some((latitude, longitude))
+}
+
+impl MetadataReader for NativeReader {
+    fn date_taken(&self, path: &Path) -> Result<NaiveDateTime, ImportError> {
+        let exif = match read_exif(path) {
+            Ok(exif) => exif,
+            // 不是图片时按 MP4/MOV 读取
+            Err(ImportError::UnsupportedFormat(detail)) => {
+                return bmff::creation_time(path)
+                    .map_err(|_| ImportError::UnsupportedFormat(detail))?
+                    .ok_or_else(|| ImportError::MetadataUnavailable("CreationTime".to_string()));
+            }
+            Err(e) => return Err(e),
+        };
+        let datetime_str = ascii(&exif, Tag::DateTimeOriginal)
+            .or_else(|| ascii(&exif, Tag::DateTime))
+            .ok_or_else(|| ImportError::MetadataUnavailable("DateTimeOriginal".to_string()))?;
+        parse_exif_datetime(&datetime_str)
+    }
+
+    fn camera(&self, path: &Path) -> Camera {
+        let Ok(exif) = read_exif(path) else {
+            return Camera::default();
+        };
+        Camera {
+            make: ascii(&exif, Tag::Make),
+            model: ascii(&exif, Tag::Model),
+            subsec: ascii(&exif, Tag::SubSecTimeOriginal),
+        }
+    }
+
+    fn gps(&self, path: &Path) -> Option<(f64, f64)> {
+        gps(&read_exif(path).ok()?)
+    }
+
+    fn orientation(&self, path: &Path) -> Option<u16> {
+        let exif = read_exif(path).ok()?;
+        let orientation = exif.get_field(Tag::Orientation, In::PRIMARY)?;
+        orientation.value.get_uint(0).map(|o| o as u16)
+    }
+
+    fn summary(&self, path: &Path) -> Result<Vec<(String, String)>, ImportError> {
+        let exif = read_exif(path)?;
+        let mut ret = SUMMARY_TAGS
+            .iter()
+            .filter_map(|(label, tag)| {
+                let field = exif.get_field(*tag, In::PRIMARY)?;
+                // 字符串不带引号显示
+                let value = ascii(&exif, *tag)
+                    .unwrap_or_else(|| field.display_value().with_unit(&exif).to_string());
+                Some((label.to_string(), value))
+            })
+            .collect::<Vec<_>>();
+        if let Some((latitude, longitude)) = gps(&exif) {
+            ret.push(("GPS".to_string(), format!("{latitude:.5}, {longitude:.5}")));
+        }
+        Ok(ret)
+    }
+
+    fn content_identifier(&self, path: &Path) -> Option<String> {
+        match read_exif(path) {
+            Ok(exif) => match &exif.get_field(Tag::MakerNote, In::PRIMARY)?.value {
+                Value::Undefined(note, _) => apple_maker_note_ascii(note, APPLE_CONTENT_IDENTIFIER),
+                _ => None,
+            },
+            // 实况照片的视频
+            Err(ImportError::UnsupportedFormat(_)) => {
+                bmff::quicktime_metadata(path, QUICKTIME_CONTENT_IDENTIFIER)
+                    .ok()
+                    .flatten()
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn preview(&self, path: &Path) -> Option<Vec<u8>> {
+        match read_rw2(path) {
+            Ok(Some(Rw2::Jpeg(jpeg))) => return Some(jpeg),
+            Ok(Some(Rw2::Raw(_))) => return None,
+            _ => {}
+        }
+        // 其他 RAW 只能取 IFD1 中的缩略图
+        let exif = read_exif(path).ok()?;
+        let offset = exif
+            .get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)?
+            .value
+            .get_uint(0)? as usize;
+        let len = exif
+            .get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?
+            .value
+            .get_uint(0)? as usize;
+        exif.buf()
+            .get(offset..offset + len)
+            .map(|data| data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_apple_content_identifier() {
+        let id = b"0C2E6A55-1D2B\0";
+        let mut note = b"Apple iOS\0\0\x01MM".to_vec();
+        note.extend_from_slice(&2u16.to_be_bytes());
+        // 一个无关的短整数条目，之后是 ContentIdentifier
+        note.extend_from_slice(&[0, 0x01, 0, 3, 0, 0, 0, 1, 0, 9, 0, 0]);
+        note.extend_from_slice(&APPLE_CONTENT_IDENTIFIER.to_be_bytes());
+        note.extend_from_slice(&2u16.to_be_bytes());
+        note.extend_from_slice(&(id.len() as u32).to_be_bytes());
+        note.extend_from_slice(&44u32.to_be_bytes());
+        note.extend_from_slice(&[0; 4]);
+        note.extend_from_slice(id);
+        assert_eq!(
+            apple_maker_note_ascii(&note, APPLE_CONTENT_IDENTIFIER).as_deref(),
+            Some("0C2E6A55-1D2B")
+        );
+    }
+}
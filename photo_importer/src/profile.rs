@@ -0,0 +1,135 @@
+use crate::volume::VolumeInfo;
+use crate::xdg;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 导入时写入照片的标签。
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TagOptions {
+    pub keywords: Vec<String>,
+    pub copyright: Option<String>,
+    pub artist: Option<String>,
+    // 0-5
+    pub rating: Option<u8>,
+}
+
+impl TagOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == TagOptions::default()
+    }
+
+    /// 命令行的值覆盖配置中的值，关键词取并集。
+    pub fn merge(&mut self, other: TagOptions) {
+        for keyword in other.keywords {
+            if !self.keywords.contains(&keyword) {
+                self.keywords.push(keyword);
+            }
+        }
+        self.copyright = other.copyright.or(self.copyright.take());
+        self.artist = other.artist.or(self.artist.take());
+        self.rating = other.rating.or(self.rating);
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(flatten)]
+    pub tags: TagOptions,
+    // 绑定的存储卡（卷 UUID 或卷标），读取这些卡时未给 --profile 也会自动使用
+    pub volumes: Vec<String>,
+}
+
+/// `~/.config/photo_importer/config.toml`：
+///
+/// ```toml
+/// [profiles.work]
+/// keywords = ["project-x"]
+/// copyright = "© 2024 Team"
+/// artist = "Danny"
+/// rating = 3
+/// volumes = ["E956-B7F2"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub profiles: HashMap<String, Profile>,
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = xdg::xdg_dir("XDG_CONFIG_HOME", ".config")?;
+    Some(config_home.join("photo_importer").join("config.toml"))
+}
+
+impl Config {
+    /// 读取配置文件；文件不存在时返回空配置。
+    pub fn load(path: &Path) -> Result<Config, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|e| format!("配置文件 {} 有误：{e}", path.display()).into())
+    }
+
+    /// 绑定到 `volume` 的 profile，按名称排序取第一个。
+    pub fn profile_for_volume(&self, volume: &VolumeInfo) -> Option<(&str, &Profile)> {
+        let mut bound = self
+            .profiles
+            .iter()
+            .filter(|(_, profile)| profile.volumes.iter().any(|v| volume.matches(v)))
+            .collect::<Vec<_>>();
+        bound.sort_by_key(|(name, _)| name.as_str());
+        bound
+            .first()
+            .map(|(name, profile)| (name.as_str(), *profile))
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, Box<dyn Error>> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| format!("配置中没有名为 {name} 的 profile").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_merge_profile() {
+        let config: Config = toml::from_str(
+            r#"
+            [profiles.work]
+            keywords = ["project-x", "team"]
+            copyright = "© 2024 Team"
+            rating = 3
+            volumes = ["E956-B7F2"]
+            "#,
+        )
+        .unwrap();
+        let mut tags = config.profile("work").unwrap().tags.clone();
+        tags.merge(TagOptions {
+            keywords: vec!["team".to_string(), "beijing".to_string()],
+            artist: Some("Danny".to_string()),
+            rating: Some(5),
+            ..Default::default()
+        });
+        assert_eq!(tags.keywords, ["project-x", "team", "beijing"]);
+        assert_eq!(tags.copyright.as_deref(), Some("© 2024 Team"));
+        assert_eq!(tags.artist.as_deref(), Some("Danny"));
+        assert_eq!(tags.rating, Some(5));
+        assert!(config.profile("home").is_err());
+
+        let mut card = VolumeInfo {
+            uuid: Some("E956-B7F2".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.profile_for_volume(&card).unwrap().0, "work");
+        card.uuid = None;
+        assert!(config.profile_for_volume(&card).is_none());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::layout::Layout;
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_RENAME: &str = "%Y%m%d_%H%M%S_{model}_{num}";
+pub const SIDECAR_EXT: &str = "xmp";
+
+pub struct RenameInput<'a> {
+    pub path: &'a Path,
+    pub date: NaiveDateTime,
+    // Exif.Photo.SubSecTimeOriginal，如 "45"
+    pub subsec: Option<String>,
+    pub model: Option<String>,
+}
+
+/// 去掉型号里重复的厂商名和空白，如 ("NIKON CORPORATION", "NIKON Z 5") -> "Z5"。
+pub fn short_model(make: Option<&str>, model: &str) -> String {
+    let brand = make.and_then(|m| m.split_whitespace().next()).unwrap_or("");
+    let model = model.trim();
+    let model = if !brand.is_empty()
+        && model.len() > brand.len()
+        && model
+            .get(..brand.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(brand))
+    {
+        &model[brand.len()..]
+    } else {
+        model
+    };
+    model
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
+
+// 相机文件名末尾的编号，如 DSC_1937 -> 1937
+fn file_number(stem: &str) -> String {
+    let digits = stem
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+    if digits.is_empty() {
+        stem.to_string()
+    } else {
+        digits.chars().rev().collect()
+    }
+}
+
+/// 同一次拍摄的文件（RAW+JPEG、实况照片）共用的键：(所在目录, 小写的文件名主干)。
+pub fn group_key(path: &Path) -> (PathBuf, String) {
+    let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    (parent, stem.to_lowercase())
+}
+
+/// 为每个输入计算新的文件名主干（不含扩展名），顺序与输入一致。
+///
+/// 同一目录下主干相同的文件（RAW+JPEG）视为一组，得到相同的新名字；
+/// 不同组渲染出相同名字时，按拍摄时间和亚秒排序后追加 `-2`、`-3`……
+pub fn assign_names(items: &[RenameInput], template: &Layout) -> Vec<String> {
+    let mut groups = HashMap::<(PathBuf, String), Vec<usize>>::new();
+    for (i, item) in items.iter().enumerate() {
+        groups.entry(group_key(item.path)).or_default().push(i);
+    }
+
+    let mut rendered = Vec::<(String, NaiveDateTime, String, &Path, &Vec<usize>)>::new();
+    for members in groups.values() {
+        // 取组内最早的时间，型号等信息取第一个有值的成员
+        let first = members.iter().min_by_key(|i| items[**i].date).unwrap();
+        let item = &items[*first];
+        let mut placeholders = HashMap::new();
+        let stem = item.path.file_stem().unwrap_or_default().to_string_lossy();
+        placeholders.insert("name", stem.to_string());
+        placeholders.insert("num", file_number(&stem));
+        if let Some(model) = members.iter().find_map(|i| items[*i].model.clone()) {
+            placeholders.insert("model", model);
+        }
+        let subsec = members
+            .iter()
+            .find_map(|i| items[*i].subsec.clone())
+            .unwrap_or_default();
+        placeholders.insert("subsec", subsec.clone());
+        let name = template
+            .render(&item.date, &placeholders)
+            .to_string_lossy()
+            .replace('/', "_");
+        rendered.push((name, item.date, subsec, item.path, members));
+    }
+    rendered.sort_by(|a, b| (&a.0, a.1, &a.2, a.3).cmp(&(&b.0, b.1, &b.2, b.3)));
+
+    let mut ret = vec![String::new(); items.len()];
+    let mut seen = HashMap::<String, usize>::new();
+    for (name, _, _, _, members) in rendered {
+        let count = seen.entry(name.clone()).or_default();
+        *count += 1;
+        let name = if *count == 1 {
+            name
+        } else {
+            format!("{name}-{count}")
+        };
+        for i in members {
+            ret[*i] = name.clone();
+        }
+    }
+    ret
+}
+
+/// 新文件名：新主干加小写扩展名。
+pub fn renamed_file_name(path: &Path, new_stem: &str) -> String {
+    match path.extension() {
+        Some(ext) => format!("{new_stem}.{}", ext.to_string_lossy().to_lowercase()),
+        None => new_stem.to_string(),
+    }
+}
+
+/// 查找与照片配套的 XMP 附属文件（`DSC_1937.xmp` 或 `DSC_1937.NEF.xmp`），
+/// 返回 (附属文件路径, 它在 `dest_stem` 之后应有的后缀)。
+pub fn find_sidecars(path: &Path) -> Vec<(PathBuf, String)> {
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut ret = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((base, ext)) = name.rsplit_once('.') else {
+            continue;
+        };
+        if !ext.eq_ignore_ascii_case(SIDECAR_EXT) {
+            continue;
+        }
+        if base.eq_ignore_ascii_case(&stem) {
+            ret.push((entry.path(), format!(".{SIDECAR_EXT}")));
+        } else if base.eq_ignore_ascii_case(&file_name) {
+            let media_ext = path.extension().unwrap_or_default().to_string_lossy();
+            ret.push((
+                entry.path(),
+                format!(".{}.{SIDECAR_EXT}", media_ext.to_lowercase()),
+            ));
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dt;
+
+    #[test]
+    fn pairs_share_names_and_collisions_get_sequence() {
+        let paths = [
+            Path::new("/card/DCIM/100NZ502/DSC_1937.JPG"),
+            Path::new("/card/DCIM/100NZ502/DSC_1937.NEF"),
+            Path::new("/card/DCIM/101NZ502/DSC_1937.JPG"),
+        ];
+        let items = [
+            RenameInput {
+                path: paths[0],
+                date: dt("2024-05-01T14:30:12"),
+                subsec: Some("50".to_string()),
+                model: Some("Z5".to_string()),
+            },
+            RenameInput {
+                path: paths[1],
+                date: dt("2024-05-01T14:30:12"),
+                subsec: None,
+                model: None,
+            },
+            RenameInput {
+                path: paths[2],
+                date: dt("2024-05-01T14:30:12"),
+                subsec: Some("10".to_string()),
+                model: Some("Z5".to_string()),
+            },
+        ];
+        let names = assign_names(&items, &Layout::new(DEFAULT_RENAME).unwrap());
+        assert_eq!(names[0], "20240501_143012_Z5_1937-2");
+        assert_eq!(names[1], "20240501_143012_Z5_1937-2");
+        assert_eq!(names[2], "20240501_143012_Z5_1937");
+        assert_eq!(
+            renamed_file_name(paths[1], &names[1]),
+            "20240501_143012_Z5_1937-2.nef"
+        );
+        assert_eq!(short_model(Some("NIKON CORPORATION"), "NIKON Z 5"), "Z5");
+        // 厂商名长度落在多字节字符中间
+        assert_eq!(short_model(Some("NIKON"), "NIK\u{fffd}Z 5"), "NIKZ5");
+    }
+}
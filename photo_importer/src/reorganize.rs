@@ -0,0 +1,171 @@
+use crate::journal::{Journal, JournalEntry};
+use crate::layout::Layout;
+use crate::{is_media, rename, scheduler};
+use chrono::NaiveDateTime;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 一次移动：媒体文件或其附属文件。
+#[derive(Debug, PartialEq, Eq)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// 找出不在 `layout` 对应目录中的文件，附属文件跟随照片移动。
+///
+/// 以 `.` 开头的目录（`.photo_importer`、`.thumbs` 等）不会被扫描；
+/// 目标已存在的文件会被跳过。
+pub fn plan(
+    root: &Path,
+    layout: &Layout,
+    date_of: impl Fn(&Path) -> Option<NaiveDateTime>,
+) -> Vec<Move> {
+    let mut moves = Vec::new();
+    let mut claimed = HashSet::<PathBuf>::new();
+    let mut targets = HashSet::<PathBuf>::new();
+    for entry in WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        if !is_media(path) {
+            continue;
+        }
+        let Some(date) = date_of(path) else {
+            continue;
+        };
+        let dir = root.join(layout.render(&date, &HashMap::new()));
+        if path.parent() == Some(dir.as_path()) {
+            continue;
+        }
+        let mut group = vec![path.to_path_buf()];
+        for (sidecar, _) in rename::find_sidecars(path) {
+            if !claimed.contains(&sidecar) {
+                group.push(sidecar);
+            }
+        }
+        let group = group
+            .into_iter()
+            .map(|from| {
+                let to = dir.join(from.file_name().unwrap_or_default());
+                Move { from, to }
+            })
+            .collect::<Vec<_>>();
+        if let Some(conflict) = group
+            .iter()
+            .find(|m| m.to.exists() || targets.contains(&m.to))
+        {
+            println!(
+                "目标已存在，跳过: {} -> {}",
+                conflict.from.display(),
+                conflict.to.display()
+            );
+            continue;
+        }
+        for m in group {
+            claimed.insert(m.from.clone());
+            targets.insert(m.to.clone());
+            moves.push(m);
+        }
+    }
+    moves
+}
+
+// 从 `dir` 向上删除空目录，直到 `root`（不含）
+fn remove_empty_dirs(root: &Path, dir: &Path) {
+    let mut dir = dir;
+    while dir != root && dir.starts_with(root) {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+        let Some(parent) = dir.parent() else { break };
+        dir = parent;
+    }
+}
+
+/// 执行移动，每一步都记入 `journal` 以便撤销；返回 (成功数, 失败数)。
+pub fn apply(root: &Path, moves: &[Move], journal: &mut Journal) -> (usize, usize) {
+    let (mut moved, mut failed) = (0, 0);
+    for m in moves {
+        let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+            let hash = scheduler::hash_file(&m.from)?.to_hex().to_string();
+            if let Some(parent) = m.to.parent() {
+                journal.create_dir_all(parent)?;
+            }
+            fs::rename(&m.from, &m.to)?;
+            journal.append(&JournalEntry::Moved {
+                from: m.from.clone(),
+                to: m.to.clone(),
+                hash,
+            })?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => {
+                println!("已移动: {} -> {}", m.from.display(), m.to.display());
+                moved += 1;
+            }
+            Err(e) => {
+                eprintln!("移动失败 {}: {}", m.from.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    for m in moves {
+        if let Some(parent) = m.from.parent() {
+            remove_empty_dirs(root, parent);
+        }
+    }
+    (moved, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::{read_journal, reorganize_dir};
+    use crate::test_util::dt;
+
+    #[test]
+    fn moves_misplaced_files_with_sidecars() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let old_dir = root.join("misc");
+        fs::create_dir_all(&old_dir).unwrap();
+        fs::create_dir_all(root.join(".thumbs")).unwrap();
+        fs::write(old_dir.join("DSC_1937.NEF"), "raw").unwrap();
+        fs::write(old_dir.join("DSC_1937.xmp"), "xmp").unwrap();
+        fs::write(root.join(".thumbs/DSC_0001.jpg"), "thumb").unwrap();
+
+        let date = dt("2024-05-01T14:30:00");
+        let layout = Layout::new(crate::layout::DEFAULT_LAYOUT).unwrap();
+        let moves = plan(root, &layout, |_| Some(date));
+        let new_dir = root.join("2024/2024-05-01");
+        assert_eq!(
+            moves,
+            [
+                Move {
+                    from: old_dir.join("DSC_1937.NEF"),
+                    to: new_dir.join("DSC_1937.NEF"),
+                },
+                Move {
+                    from: old_dir.join("DSC_1937.xmp"),
+                    to: new_dir.join("DSC_1937.xmp"),
+                },
+            ]
+        );
+
+        let mut journal = Journal::create_in(&reorganize_dir(root), date, root).unwrap();
+        assert_eq!(apply(root, &moves, &mut journal), (2, 0));
+        assert!(new_dir.join("DSC_1937.NEF").exists());
+        assert!(!old_dir.exists());
+        // Started、两级 CreatedDir、两次 Moved
+        assert_eq!(read_journal(journal.path()).unwrap().len(), 5);
+        assert!(plan(root, &layout, |_| Some(date)).is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// 导入报告中的一行，对应一个源文件的处理结果。
+#[derive(Clone, Debug)]
+pub struct ReportRow {
+    pub source: PathBuf,
+    pub destination: Option<PathBuf>,
+    pub date: Option<NaiveDateTime>,
+    pub location: Option<String>,
+    // 所在近似照片组的代表
+    pub similar_group: Option<PathBuf>,
+    pub status: String,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `volume` 为来源卷的标识，每行相同，便于合并多次导入的报告。
+pub fn write_csv(path: &Path, volume: &str, rows: &[ReportRow]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "source,destination,date,location,similar_group,status,volume"
+    )?;
+    for row in rows {
+        let fields = [
+            row.source.to_string_lossy().to_string(),
+            row.destination
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            row.date
+                .map(|d| d.format("%Y-%m-%dT%H:%M:%S").to_string())
+                .unwrap_or_default(),
+            row.location.clone().unwrap_or_default(),
+            row.similar_group
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            row.status.clone(),
+            volume.to_string(),
+        ];
+        let line = fields
+            .iter()
+            .map(|f| csv_field(f))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(out, "{line}")?;
+    }
+    out.flush()
+}
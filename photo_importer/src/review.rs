@@ -0,0 +1,345 @@
+use crate::metadata;
+use crate::report::ReportRow;
+use crate::{ImageInfo, cluster};
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs, Wrap};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const HELP: &str =
+    "↑↓ 移动  空格 选择/取消  回车 展开/折叠  i EXIF  Tab 跳过列表  y 开始导入  q 取消";
+
+struct Group {
+    label: String,
+    // 组内照片在输入中的下标
+    files: Vec<usize>,
+    expanded: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Row {
+    Group(usize),
+    File(usize, usize),
+}
+
+/// 按事件（分组模式）或日期归组的待导入照片及其选中状态。
+struct Selection {
+    groups: Vec<Group>,
+    selected: Vec<bool>,
+}
+
+impl Selection {
+    fn new(infos: &[ImageInfo]) -> Selection {
+        let mut order = (0..infos.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| (infos[*i].date, *i));
+        let mut groups = Vec::<Group>::new();
+        for i in order {
+            let label = match infos[i].event {
+                Some(event) => event.format(cluster::EVENT_FMT).to_string(),
+                None => infos[i].date.format("%Y-%m-%d").to_string(),
+            };
+            match groups.iter_mut().find(|g| g.label == label) {
+                Some(group) => group.files.push(i),
+                None => groups.push(Group {
+                    label,
+                    files: vec![i],
+                    expanded: false,
+                }),
+            }
+        }
+        Selection {
+            groups,
+            selected: vec![true; infos.len()],
+        }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (g, group) in self.groups.iter().enumerate() {
+            rows.push(Row::Group(g));
+            if group.expanded {
+                rows.extend(group.files.iter().map(|i| Row::File(g, *i)));
+            }
+        }
+        rows
+    }
+
+    fn selected_in(&self, group: usize) -> usize {
+        let files = &self.groups[group].files;
+        files.iter().filter(|i| self.selected[**i]).count()
+    }
+
+    // 整组都已选中时取消整组，否则选中整组
+    fn toggle(&mut self, row: Row) {
+        match row {
+            Row::Group(g) => {
+                let select = self.selected_in(g) < self.groups[g].files.len();
+                for i in &self.groups[g].files {
+                    self.selected[*i] = select;
+                }
+            }
+            Row::File(_, i) => self.selected[i] = !self.selected[i],
+        }
+    }
+}
+
+fn read_exif(path: &Path) -> Vec<(String, String)> {
+    metadata::reader()
+        .summary(path)
+        .unwrap_or_else(|_| vec![("错误".to_string(), "无法读取元数据".to_string())])
+}
+
+struct App<'a> {
+    infos: &'a [ImageInfo],
+    skipped: &'a [ReportRow],
+    selection: Selection,
+    list: ListState,
+    skipped_list: ListState,
+    show_skipped: bool,
+    show_exif: bool,
+    exif_cache: HashMap<PathBuf, Vec<(String, String)>>,
+}
+
+impl App<'_> {
+    fn current(&self) -> Option<Row> {
+        self.list
+            .selected()
+            .and_then(|i| self.selection.rows().get(i).copied())
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        let [tabs_area, main_area, help_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(1),
+                Constraint::Length(1),
+            ])
+            .areas(frame.area());
+        let chosen = self.selection.selected.iter().filter(|s| **s).count();
+        let tabs = Tabs::new([
+            format!("待导入 {chosen}/{}", self.infos.len()),
+            format!("跳过 {}", self.skipped.len()),
+        ])
+        .select(usize::from(self.show_skipped))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_widget(tabs, tabs_area);
+        frame.render_widget(Paragraph::new(HELP), help_area);
+
+        if self.show_skipped {
+            self.draw_skipped(frame, main_area);
+            return;
+        }
+        let (list_area, exif_area) = if self.show_exif {
+            let [list_area, exif_area] = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main_area);
+            (list_area, Some(exif_area))
+        } else {
+            (main_area, None)
+        };
+        self.draw_plan(frame, list_area);
+        if let Some(area) = exif_area {
+            self.draw_exif(frame, area);
+        }
+    }
+
+    fn draw_plan(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let items = self
+            .selection
+            .rows()
+            .into_iter()
+            .map(|row| match row {
+                Row::Group(g) => {
+                    let group = &self.selection.groups[g];
+                    let count = self.selection.selected_in(g);
+                    let mark = match count {
+                        0 => ' ',
+                        n if n == group.files.len() => 'x',
+                        _ => '-',
+                    };
+                    let arrow = if group.expanded { '▾' } else { '▸' };
+                    ListItem::new(format!(
+                        "{arrow} [{mark}] {}  ({count}/{})",
+                        group.label,
+                        group.files.len()
+                    ))
+                }
+                Row::File(_, i) => {
+                    let info = &self.infos[i];
+                    let mark = if self.selection.selected[i] { 'x' } else { ' ' };
+                    let name = info.path.file_name().unwrap_or_default().to_string_lossy();
+                    let mut line = format!("    [{mark}] {} {name}", info.date.format("%H:%M:%S"));
+                    if let Some(location) = info.location() {
+                        line.push_str(&format!("  {location}"));
+                    }
+                    if let Some(group) = &info.similar_group
+                        && *group != info.path
+                    {
+                        line.push_str("  (近似)");
+                    }
+                    ListItem::new(line)
+                }
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("导入计划"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_exif(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let lines = match self.current() {
+            Some(Row::File(_, i)) => {
+                let path = &self.infos[i].path;
+                let exif = self
+                    .exif_cache
+                    .entry(path.clone())
+                    .or_insert_with(|| read_exif(path));
+                let mut lines = vec![Line::from(path.to_string_lossy().to_string())];
+                lines.extend(
+                    exif.iter()
+                        .map(|(label, value)| Line::from(format!("{label}: {value}"))),
+                );
+                lines
+            }
+            _ => vec![Line::from("选择一个文件查看 EXIF")],
+        };
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("EXIF"));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_skipped(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let items = self
+            .skipped
+            .iter()
+            .map(|row| ListItem::new(format!("{}  {}", row.source.display(), row.status)))
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("跳过的文件"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.skipped_list);
+    }
+
+    // 返回 Some(true) 开始导入，Some(false) 取消
+    fn handle_key(&mut self, code: KeyCode) -> Option<bool> {
+        let list = if self.show_skipped {
+            &mut self.skipped_list
+        } else {
+            &mut self.list
+        };
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => list.select_next(),
+            KeyCode::PageUp => list.scroll_up_by(20),
+            KeyCode::PageDown => list.scroll_down_by(20),
+            KeyCode::Tab => self.show_skipped = !self.show_skipped,
+            KeyCode::Char('i') => self.show_exif = !self.show_exif,
+            KeyCode::Char(' ') if !self.show_skipped => {
+                if let Some(row) = self.current() {
+                    self.selection.toggle(row);
+                }
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Left if !self.show_skipped => {
+                let g = match self.current() {
+                    Some(Row::Group(g) | Row::File(g, _)) => g,
+                    None => return None,
+                };
+                let group = &mut self.selection.groups[g];
+                group.expanded = !group.expanded;
+                // 折叠后光标回到组标题
+                let rows = self.selection.rows();
+                self.list
+                    .select(rows.iter().position(|row| *row == Row::Group(g)));
+            }
+            KeyCode::Char('y') => return Some(true),
+            KeyCode::Char('q') | KeyCode::Esc => return Some(false),
+            _ => {}
+        }
+        None
+    }
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<bool> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && let Some(confirmed) = app.handle_key(key.code)
+        {
+            return Ok(confirmed);
+        }
+    }
+}
+
+/// 在终端界面中审阅导入计划。确认后返回每张照片是否选中，取消时返回 `None`。
+pub fn run(infos: &[ImageInfo], skipped: &[ReportRow]) -> io::Result<Option<Vec<bool>>> {
+    let mut app = App {
+        infos,
+        skipped,
+        selection: Selection::new(infos),
+        list: ListState::default().with_selected(Some(0)),
+        skipped_list: ListState::default().with_selected(Some(0)),
+        show_skipped: false,
+        show_exif: false,
+        exif_cache: HashMap::new(),
+    };
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut app);
+    ratatui::try_restore()?;
+    Ok(result?.then_some(app.selection.selected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn info(path: &str, date: &str) -> ImageInfo {
+        ImageInfo {
+            path: PathBuf::from(path),
+            date: NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").unwrap(),
+            gps: None,
+            place: None,
+            event: None,
+            phash: None,
+            similar_group: None,
+            new_stem: None,
+            live_video: None,
+        }
+    }
+
+    #[test]
+    fn toggles_days_and_files() {
+        let infos = [
+            info("b.jpg", "2024-05-02T09:00:00"),
+            info("a.jpg", "2024-05-01T10:00:00"),
+            info("c.jpg", "2024-05-02T08:00:00"),
+        ];
+        let mut selection = Selection::new(&infos);
+        assert_eq!(selection.rows(), [Row::Group(0), Row::Group(1)]);
+        assert_eq!(selection.groups[1].files, [2, 0]);
+
+        selection.toggle(Row::File(1, 2));
+        assert_eq!(selection.selected, [true, true, false]);
+        // 部分选中的组再切换时全部选中，全部选中时全部取消
+        selection.toggle(Row::Group(1));
+        assert_eq!(selection.selected, [true, true, true]);
+        selection.toggle(Row::Group(1));
+        assert_eq!(selection.selected, [false, true, false]);
+
+        selection.groups[0].expanded = true;
+        assert_eq!(
+            selection.rows(),
+            [Row::Group(0), Row::File(0, 1), Row::Group(1)]
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
+
+// 超过这个大小的文件（多为视频）不读入内存，由读线程分块写入所有目标
+const BUFFER_LIMIT: u64 = 64 * 1024 * 1024;
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// 读写并发配置。读线程按源设备分组，同一设备上的文件按路径顺序读取。
+#[derive(Clone, Copy, Debug)]
+pub struct IoOptions {
+    // 每个源设备的读线程数
+    pub readers: usize,
+    // 写线程总数
+    pub writers: usize,
+}
+
+impl Default for IoOptions {
+    fn default() -> Self {
+        IoOptions {
+            readers: 1,
+            writers: 2,
+        }
+    }
+}
+
+/// 一个源文件及其所有目标；源文件只读取一次。
+pub struct CopyJob {
+    pub source: PathBuf,
+    pub destinations: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyMethod {
+    Reflink,
+    CopyFileRange,
+    Buffered,
+}
+
+/// 一次成功写入并校验过的复制。
+#[derive(Clone, Debug)]
+pub struct Copied {
+    pub method: CopyMethod,
+    // 源文件内容的 blake3 哈希（十六进制）
+    pub hash: String,
+}
+
+pub struct CopyStats {
+    // 从源设备读取的字节数
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl CopyStats {
+    pub fn mib_per_sec(&self) -> f64 {
+        self.bytes as f64 / 1024.0 / 1024.0 / self.elapsed.as_secs_f64().max(0.001)
+    }
+}
+
+struct WriteTask<'a> {
+    job: &'a CopyJob,
+    index: usize,
+    data: Arc<Vec<u8>>,
+    hash: blake3::Hash,
+}
+
+fn device_of(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.dev()).unwrap_or_default()
+}
+
+fn same_device(source: &Path, destination: &Path) -> bool {
+    let dst_dir = destination.parent().unwrap_or(Path::new("."));
+    device_of(source) == device_of(dst_dir)
+}
+
+/// 新建目标文件并写入；写入失败时删掉不完整的文件。已存在的文件不会被覆盖。
+fn fill_destination<T>(path: &Path, f: impl FnOnce(&File) -> io::Result<T>) -> io::Result<T> {
+    let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    let result = f(&file).and_then(|ret| file.sync_all().map(|_| ret));
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    result
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(source: &File, destination: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    let ret = unsafe { libc::ioctl(destination.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &File, _destination: &File) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// 在内核中复制；返回 `Ok(false)` 表示不支持，且尚未写入任何数据。
+#[cfg(target_os = "linux")]
+fn copy_file_range(source: &File, destination: &File, len: u64) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+    let mut copied = 0u64;
+    while copied < len {
+        let ret = unsafe {
+            libc::copy_file_range(
+                source.as_raw_fd(),
+                std::ptr::null_mut(),
+                destination.as_raw_fd(),
+                std::ptr::null_mut(),
+                (len - copied) as usize,
+                0,
+            )
+        };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            let unsupported = matches!(
+                err.raw_os_error(),
+                Some(libc::EXDEV | libc::ENOSYS | libc::EOPNOTSUPP | libc::EINVAL)
+            );
+            if copied == 0 && unsupported {
+                return Ok(false);
+            }
+            return Err(err);
+        }
+        if ret == 0 {
+            break;
+        }
+        copied += ret as u64;
+    }
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_file_range(_source: &File, _destination: &File, _len: u64) -> io::Result<bool> {
+    Ok(false)
+}
+
+// 让校验读到的是磁盘上的数据，而不是页缓存
+#[cfg(target_os = "linux")]
+fn drop_page_cache(file: &File) {
+    use std::os::fd::AsRawFd;
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_page_cache(_file: &File) {}
+
+pub fn hash_file(path: &Path) -> io::Result<blake3::Hash> {
+    let file = File::open(path)?;
+    drop_page_cache(&file);
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(file)?;
+    Ok(hasher.finalize())
+}
+
+/// 重新读取目标文件并与源哈希比较，不一致时删除目标文件。
+fn verify(destination: &Path, expected: &blake3::Hash) -> io::Result<()> {
+    if hash_file(destination)? == *expected {
+        return Ok(());
+    }
+    let _ = fs::remove_file(destination);
+    Err(io::Error::other("校验失败，目标文件与源文件不一致"))
+}
+
+/// 写线程处理一个目标。与源在同一设备时优先在内核中复制。
+fn write_one(task: &WriteTask) -> io::Result<Copied> {
+    let source = &task.job.source;
+    let destination = &task.job.destinations[task.index];
+    let method = fill_destination(destination, |mut file| {
+        if same_device(source, destination) {
+            let src_file = File::open(source)?;
+            if reflink(&src_file, file).is_ok() {
+                return Ok(CopyMethod::Reflink);
+            }
+            if copy_file_range(&src_file, file, task.data.len() as u64)? {
+                return Ok(CopyMethod::CopyFileRange);
+            }
+        }
+        file.write_all(&task.data)?;
+        Ok(CopyMethod::Buffered)
+    })?;
+    verify(destination, &task.hash)?;
+    Ok(Copied {
+        method,
+        hash: task.hash.to_hex().to_string(),
+    })
+}
+
+/// 大文件：边读边写入所有目标，源文件仍只读一次。
+fn stream_one(job: &CopyJob, source: File) -> Vec<io::Result<Copied>> {
+    let mut source = io::BufReader::with_capacity(CHUNK_SIZE, source);
+    let mut outputs = job
+        .destinations
+        .iter()
+        .map(|d| OpenOptions::new().write(true).create_new(true).open(d))
+        .collect::<Vec<_>>();
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let read_error = loop {
+        let n = match source.read(&mut buf) {
+            Ok(0) => break None,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => break Some(e),
+        };
+        hasher.update(&buf[..n]);
+        for output in outputs.iter_mut() {
+            if let Ok(file) = output
+                && let Err(e) = file.write_all(&buf[..n])
+            {
+                *output = Err(e);
+            }
+        }
+    };
+    let hash = hasher.finalize();
+    outputs
+        .into_iter()
+        .zip(&job.destinations)
+        .map(|(output, destination)| {
+            let result = match (&read_error, output) {
+                (Some(e), Ok(_)) => Err(io::Error::new(e.kind(), e.to_string())),
+                (_, Ok(file)) => file.sync_all().and_then(|_| verify(destination, &hash)),
+                (_, Err(e)) => Err(e),
+            };
+            // 只删除本次创建的文件
+            if let Err(e) = &result
+                && e.kind() != io::ErrorKind::AlreadyExists
+            {
+                let _ = fs::remove_file(destination);
+            }
+            result.map(|_| Copied {
+                method: CopyMethod::Buffered,
+                hash: hash.to_hex().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 执行复制任务。每个 (任务, 目标序号) 完成或失败时调用一次 `on_done`，返回总吞吐量。
+pub fn run<F>(jobs: &[CopyJob], options: &IoOptions, on_done: F) -> CopyStats
+where
+    F: Fn(&CopyJob, usize, io::Result<Copied>) + Sync,
+{
+    let start = Instant::now();
+    let bytes = AtomicU64::new(0);
+
+    // 按源设备分组，组内按路径排序以尽量顺序读取
+    let mut queues = HashMap::<u64, Vec<&CopyJob>>::new();
+    for job in jobs {
+        queues.entry(device_of(&job.source)).or_default().push(job);
+    }
+    let queues = queues
+        .into_values()
+        .map(|mut queue| {
+            queue.sort_by(|a, b| a.source.cmp(&b.source));
+            Mutex::new(VecDeque::from(queue))
+        })
+        .collect::<Vec<_>>();
+
+    let readers = options.readers.max(1);
+    let writers = options.writers.max(1);
+    let (sender, receiver) = mpsc::sync_channel::<WriteTask>(writers * 2);
+    let receiver = Mutex::new(receiver);
+
+    std::thread::scope(|scope| {
+        for _ in 0..writers {
+            scope.spawn(|| {
+                loop {
+                    let task = receiver.lock().unwrap().recv();
+                    let Ok(task) = task else {
+                        break;
+                    };
+                    on_done(task.job, task.index, write_one(&task));
+                }
+            });
+        }
+        for queue in &queues {
+            for _ in 0..readers {
+                let sender = sender.clone();
+                let (bytes, on_done) = (&bytes, &on_done);
+                scope.spawn(move || {
+                    loop {
+                        let job = queue.lock().unwrap().pop_front();
+                        let Some(job) = job else {
+                            break;
+                        };
+                        let report_all = |e: io::Error| {
+                            for index in 0..job.destinations.len() {
+                                on_done(job, index, Err(io::Error::new(e.kind(), e.to_string())));
+                            }
+                        };
+                        let opened = File::open(&job.source)
+                            .and_then(|file| file.metadata().map(|m| (file, m.len())));
+                        let (mut source, len) = match opened {
+                            Ok(opened) => opened,
+                            Err(e) => {
+                                report_all(e);
+                                continue;
+                            }
+                        };
+                        if len > BUFFER_LIMIT {
+                            for (index, result) in stream_one(job, source).into_iter().enumerate() {
+                                on_done(job, index, result);
+                            }
+                            bytes.fetch_add(len, Ordering::Relaxed);
+                            continue;
+                        }
+                        let mut data = Vec::with_capacity(len as usize);
+                        if let Err(e) = source.read_to_end(&mut data) {
+                            report_all(e);
+                            continue;
+                        }
+                        bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+                        let hash = blake3::hash(&data);
+                        let data = Arc::new(data);
+                        for index in 0..job.destinations.len() {
+                            let task = WriteTask {
+                                job,
+                                index,
+                                data: data.clone(),
+                                hash,
+                            };
+                            // 写线程都已退出时 send 才会失败，此时只能就地报告
+                            if let Err(mpsc::SendError(task)) = sender.send(task) {
+                                on_done(job, task.index, Err(io::Error::other("写线程已退出")));
+                            }
+                        }
+                    }
+                });
+            }
+        }
+        drop(sender);
+    });
+
+    CopyStats {
+        bytes: bytes.into_inner(),
+        elapsed: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_all_jobs_to_all_destinations() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let (src, dst1, dst2) = (dir.join("src"), dir.join("dst1"), dir.join("dst2"));
+        for d in [&src, &dst1, &dst2] {
+            fs::create_dir_all(d).unwrap();
+        }
+        let jobs = (0..5)
+            .map(|i| {
+                let source = src.join(format!("{i}.jpg"));
+                fs::write(&source, vec![i as u8; 1000 * (i + 1)]).unwrap();
+                CopyJob {
+                    source,
+                    destinations: vec![
+                        dst1.join(format!("{i}.jpg")),
+                        dst2.join(format!("{i}.jpg")),
+                    ],
+                }
+            })
+            .collect::<Vec<_>>();
+        fs::write(dst2.join("4.jpg"), b"existing").unwrap();
+
+        let failed = Mutex::new(Vec::new());
+        let stats = run(&jobs, &IoOptions::default(), |job, index, result| {
+            if result.is_err() {
+                failed.lock().unwrap().push(job.destinations[index].clone());
+            }
+        });
+        assert_eq!(failed.into_inner().unwrap(), [dst2.join("4.jpg")]);
+        assert_eq!(stats.bytes, 1000 + 2000 + 3000 + 4000 + 5000);
+        assert_eq!(fs::read(dst1.join("2.jpg")).unwrap(), vec![2u8; 3000]);
+        assert_eq!(fs::read(dst2.join("2.jpg")).unwrap(), vec![2u8; 3000]);
+        assert_eq!(fs::read(dst1.join("4.jpg")).unwrap(), vec![4u8; 5000]);
+        assert_eq!(fs::read(dst2.join("4.jpg")).unwrap(), b"existing");
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{Duration, NaiveDateTime};
+use image::DynamicImage;
+use image::imageops::FilterType;
+use std::path::Path;
+
+use crate::thumbnail;
+
+// 汉明距离不超过此值视为近似重复（64 位中）
+pub const DEFAULT_MAX_DISTANCE: u32 = 10;
+// 连拍中相邻两张的最大间隔
+pub const BURST_GAP: Duration = Duration::seconds(2);
+
+/// dHash：缩到 9x8 灰度，逐行比较相邻像素的明暗。
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// 对 JPEG 或 RAW 内嵌预览计算感知哈希；其他格式或读取失败返回 `None`。
+pub fn compute(path: &Path) -> Option<u64> {
+    match thumbnail::load_preview(path) {
+        Ok(preview) => preview.map(|image| dhash(&image)),
+        Err(_) => None,
+    }
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[i] = root;
+    root
+}
+
+// 合并后每个元素指向组内下标最小者；单独成组的为 None
+fn representatives(mut parent: Vec<usize>) -> Vec<Option<usize>> {
+    let n = parent.len();
+    let roots = (0..n).map(|i| find(&mut parent, i)).collect::<Vec<_>>();
+    let mut first = vec![usize::MAX; n];
+    let mut size = vec![0; n];
+    for (i, root) in roots.iter().enumerate() {
+        first[*root] = first[*root].min(i);
+        size[*root] += 1;
+    }
+    roots
+        .iter()
+        .map(|root| (size[*root] > 1).then_some(first[*root]))
+        .collect()
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    if a != b {
+        parent[a.max(b)] = a.min(b);
+    }
+}
+
+/// 不论拍摄时间，找出画面近似的照片（如手机和相机各导出一份）。
+///
+/// 返回每张照片所在组的代表（组内第一张）的下标，没有近似照片的为 `None`。
+pub fn find_similar(hashes: &[Option<u64>], max_distance: u32) -> Vec<Option<usize>> {
+    let mut parent = (0..hashes.len()).collect::<Vec<_>>();
+    for (i, a) in hashes.iter().enumerate() {
+        let Some(a) = a else { continue };
+        for (j, b) in hashes.iter().enumerate().skip(i + 1) {
+            if let Some(b) = b
+                && distance(*a, *b) <= max_distance
+            {
+                union(&mut parent, i, j);
+            }
+        }
+    }
+    representatives(parent)
+}
+
+/// 找出连拍：按时间排序后，与前一张间隔不超过 `BURST_GAP` 且画面近似的归为一组。
+///
+/// 返回值含义同 [`find_similar`]，代表为组内最早的一张。
+pub fn find_bursts(
+    items: &[(NaiveDateTime, Option<u64>)],
+    max_distance: u32,
+) -> Vec<Option<usize>> {
+    let mut order = (0..items.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| (items[*i].0, *i));
+    let mut parent = (0..items.len()).collect::<Vec<_>>();
+    for pair in order.windows(2) {
+        let ((prev_date, prev_hash), (date, hash)) = (items[pair[0]], items[pair[1]]);
+        if let (Some(a), Some(b)) = (prev_hash, hash)
+            && date - prev_date <= BURST_GAP
+            && distance(a, b) <= max_distance
+        {
+            union(&mut parent, pair[0], pair[1]);
+        }
+    }
+    // 代表取最早的一张而不是下标最小的
+    let groups = representatives(parent);
+    let mut earliest = vec![None::<usize>; items.len()];
+    for i in order {
+        if let Some(rep) = groups[i] {
+            earliest[rep].get_or_insert(i);
+        }
+    }
+    groups
+        .iter()
+        .map(|g| g.and_then(|rep| earliest[rep]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dt;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn groups_similar_images_and_bursts() {
+        let gradient = |offset: u8| {
+            DynamicImage::ImageLuma8(GrayImage::from_fn(90, 80, |x, _| {
+                Luma([(x as u8).saturating_mul(2).saturating_add(offset)])
+            }))
+        };
+        let flipped = gradient(0).fliph();
+        let a = dhash(&gradient(0));
+        let b = dhash(&gradient(10));
+        let c = dhash(&flipped);
+        assert!(distance(a, b) <= DEFAULT_MAX_DISTANCE);
+        assert!(distance(a, c) > DEFAULT_MAX_DISTANCE);
+
+        let hashes = [Some(c), Some(a), None, Some(b)];
+        assert_eq!(
+            find_similar(&hashes, DEFAULT_MAX_DISTANCE),
+            [None, Some(1), None, Some(1)]
+        );
+
+        let items = [
+            (dt("2024-05-01T14:30:01"), Some(b)),
+            (dt("2024-05-01T14:30:00"), Some(a)),
+            (dt("2024-05-01T14:30:02"), Some(c)),
+            (dt("2024-05-01T15:00:00"), Some(a)),
+        ];
+        assert_eq!(
+            find_bursts(&items, DEFAULT_MAX_DISTANCE),
+            [Some(1), Some(1), None, None]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use crate::journal::{self, JournalEntry};
+use crate::xdg;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const REASON_DECLINED: &str = "未选择";
+pub const REASON_DELETED: &str = "导入后被删除";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkipEntry {
+    pub source: PathBuf,
+    pub reason: String,
+    pub time: NaiveDateTime,
+}
+
+/// 某个来源卷上不再导入的文件，按内容的 blake3 哈希记录，改名或换目录也能认出。
+#[derive(Default, Serialize, Deserialize)]
+pub struct SkipList {
+    #[serde(skip)]
+    path: PathBuf,
+    pub entries: BTreeMap<String, SkipEntry>,
+    // 清除过的哈希，不再从旧的删除记录中学回来
+    #[serde(default)]
+    allowed: BTreeSet<String>,
+}
+
+fn skip_dir() -> Option<PathBuf> {
+    let data_home = xdg::xdg_dir("XDG_DATA_HOME", ".local/share")?;
+    Some(data_home.join("photo_importer").join("skip"))
+}
+
+fn file_name(volume: &str) -> String {
+    format!("{}.json", volume.replace(['/', '\\'], "_"))
+}
+
+/// 有跳过列表的来源卷。
+pub fn list_volumes() -> io::Result<Vec<String>> {
+    let Some(dir) = skip_dir().filter(|dir| dir.exists()) else {
+        return Ok(Vec::new());
+    };
+    let mut volumes = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .filter_map(|p| Some(p.file_stem()?.to_string_lossy().to_string()))
+        .collect::<Vec<_>>();
+    volumes.sort();
+    Ok(volumes)
+}
+
+impl SkipList {
+    pub fn load(volume: &str) -> io::Result<SkipList> {
+        let dir = skip_dir().ok_or_else(|| io::Error::other("找不到数据目录"))?;
+        SkipList::load_from(&dir.join(file_name(volume)))
+    }
+
+    fn load_from(path: &Path) -> io::Result<SkipList> {
+        let mut list = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(io::Error::other)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => SkipList::default(),
+            Err(e) => return Err(e),
+        };
+        list.path = path.to_path_buf();
+        Ok(list)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&self.path, text)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&SkipEntry> {
+        self.entries.get(hash)
+    }
+
+    /// 明确拒绝的文件，即使以前清除过也重新加入。
+    pub fn add(&mut self, hash: String, entry: SkipEntry) {
+        self.allowed.remove(&hash);
+        self.entries.insert(hash, entry);
+    }
+
+    /// 是否还能从导入记录中学到：既不在列表中，也没有被清除或否认过。
+    pub fn can_learn(&self, hash: &str) -> bool {
+        !self.allowed.contains(hash) && !self.entries.contains_key(hash)
+    }
+
+    /// 从导入记录中学到的文件，清除过的不再加入。返回是否为新加入。
+    pub fn learn(&mut self, hash: String, entry: SkipEntry) -> bool {
+        if !self.can_learn(&hash) {
+            return false;
+        }
+        self.entries.insert(hash, entry);
+        true
+    }
+
+    /// 用户否认是删除的文件（如手动移走），以后不再询问。
+    pub fn allow(&mut self, hash: String) {
+        if !self.entries.contains_key(&hash) {
+            self.allowed.insert(hash);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        let hashes = std::mem::take(&mut self.entries).into_keys();
+        self.allowed.extend(hashes);
+    }
+}
+
+/// 从 `dst_root` 的导入记录中找出来自 `volume`、复制后又被删除的文件：(哈希, 源路径, 导入时间)。
+/// 被 reorganize 移走的不算。
+pub fn deleted_after_import(
+    dst_root: &Path,
+    volume: &str,
+) -> io::Result<Vec<(String, PathBuf, NaiveDateTime)>> {
+    let mut moved = HashSet::new();
+    let reorganize_dir = journal::reorganize_dir(dst_root);
+    if reorganize_dir.exists() {
+        for entry in fs::read_dir(reorganize_dir)?.filter_map(|e| e.ok()) {
+            if entry.path().extension().is_some_and(|e| e == "jsonl") {
+                for entry in journal::read_journal(&entry.path())? {
+                    if let JournalEntry::Moved { from, .. } = entry {
+                        moved.insert(from);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut deleted = Vec::new();
+    for path in journal::list_journals(dst_root)? {
+        let entries = journal::read_journal(&path)?;
+        let Some(JournalEntry::Started {
+            time,
+            volume: Some(journal_volume),
+            ..
+        }) = entries.first()
+        else {
+            continue;
+        };
+        if journal_volume != volume {
+            continue;
+        }
+        for entry in &entries {
+            if let JournalEntry::Copied {
+                source,
+                destination,
+                hash,
+            } = entry
+                && !hash.is_empty()
+                && !destination.exists()
+                && !moved.contains(destination)
+            {
+                deleted.push((hash.clone(), source.clone(), *time));
+            }
+        }
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::Journal;
+
+    #[test]
+    fn learns_deleted_files_and_clears() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let time = NaiveDateTime::default();
+        let mut journal = Journal::create(root, time, Path::new("/card"), "card").unwrap();
+        for (name, hash) in [("a.jpg", "aa"), ("b.jpg", "bb")] {
+            journal
+                .append(&JournalEntry::Copied {
+                    source: Path::new("/card").join(name),
+                    destination: root.join(name),
+                    hash: hash.to_string(),
+                })
+                .unwrap();
+        }
+        fs::write(root.join("a.jpg"), "a").unwrap();
+        let deleted = deleted_after_import(root, "card").unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].0, "bb");
+        assert!(deleted_after_import(root, "other").unwrap().is_empty());
+
+        let mut list = SkipList::load_from(&root.join("skip.json")).unwrap();
+        let entry = |source: &Path| SkipEntry {
+            source: source.to_path_buf(),
+            reason: REASON_DELETED.to_string(),
+            time,
+        };
+        assert!(list.learn("bb".to_string(), entry(&deleted[0].1)));
+        list.save().unwrap();
+        let mut list = SkipList::load_from(&root.join("skip.json")).unwrap();
+        assert!(list.get("bb").is_some());
+        list.clear();
+        assert!(!list.learn("bb".to_string(), entry(&deleted[0].1)));
+        list.add("bb".to_string(), entry(&deleted[0].1));
+        assert!(list.get("bb").is_some());
+        list.allow("cc".to_string());
+        assert!(!list.can_learn("cc"));
+    }
+}
@@ -0,0 +1,780 @@
+use crate::exclude::{Excludes, IgnoreFiles, ScanOptions};
+use crate::volume::{self, VolumeInfo};
+use chrono::{Local, NaiveDate, TimeZone};
+use filetime::FileTime;
+use flate2::read::GzDecoder;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+/// 照片的来源：挂载的文件系统、压缩包、通过 PTP/MTP 连接的设备等。
+pub trait Source: Send + Sync {
+    /// 写入导入记录的来源描述
+    fn describe(&self) -> String;
+
+    /// 记入 XMP 的来源卷名
+    fn volume_label(&self) -> String {
+        self.describe()
+    }
+
+    /// 来源所在的卷，只有本地文件系统能查到
+    fn volume(&self) -> Option<VolumeInfo> {
+        None
+    }
+
+    /// 区分不同存储卡的标识，用于导入记录和跳过列表：卷的 UUID，查不到时为卷名
+    fn volume_id(&self) -> String {
+        self.volume()
+            .and_then(|v| v.id().map(str::to_string))
+            .unwrap_or_else(|| self.volume_label())
+    }
+
+    /// 列出源中的所有文件
+    fn list(&self) -> io::Result<Vec<PathBuf>>;
+
+    /// `list` 返回的是否为本机文件系统中可直接读取的路径；否则需先经 `read` 取到本地
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Box<dyn Read + '_>>;
+
+    /// 来源中记录的修改时间，取到本地的文件沿用它；需在 `list` 或 `visit` 读到该文件之后调用
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+
+    /// 依次读取 `wanted` 选中的文件。默认逐个调用 `read`，压缩包等只能顺序读取的来源可以一次遍历完成。
+    fn visit(
+        &self,
+        wanted: &dyn Fn(&Path) -> bool,
+        f: &mut dyn FnMut(&Path, &mut dyn Read),
+    ) -> io::Result<()> {
+        for path in self.list()? {
+            if !wanted(&path) {
+                continue;
+            }
+            match self.read(&path) {
+                Ok(mut reader) => f(&path, &mut reader),
+                Err(e) => eprintln!("读取失败 {}: {}", path.display(), e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 根据命令行中的 `<from>` 选择来源：`gphoto2:[port]` 为 PTP/MTP 设备，
+/// zip、tar、tar.gz 文件为压缩包，其余为本地目录。
+pub fn open(spec: &str, scan: &ScanOptions) -> Box<dyn Source> {
+    if let Some(port) = spec.strip_prefix("gphoto2:") {
+        let port = Some(port).filter(|p| !p.is_empty());
+        return Box::new(Gphoto2Source::new(port, scan.excludes.clone()));
+    }
+    let path = PathBuf::from(spec);
+    match ArchiveKind::detect(&path) {
+        Some(kind) if path.is_file() => {
+            Box::new(ArchiveSource::new(path, kind, scan.excludes.clone()))
+        }
+        _ => Box::new(FsSource {
+            root: path,
+            scan: scan.clone(),
+        }),
+    }
+}
+
+pub struct FsSource {
+    pub root: PathBuf,
+    pub scan: ScanOptions,
+}
+
+impl Source for FsSource {
+    fn describe(&self) -> String {
+        self.root.to_string_lossy().to_string()
+    }
+
+    // 卷标，没有时为挂载点的目录名，如 /run/media/danny/E956-B7F2 -> E956-B7F2
+    fn volume_label(&self) -> String {
+        if let Some(label) = self.volume().and_then(|v| v.label) {
+            return label;
+        }
+        let path = fs::canonicalize(&self.root).unwrap_or_else(|_| self.root.clone());
+        let device = |p: &Path| fs::metadata(p).map(|m| m.dev()).ok();
+        path.ancestors()
+            .find(|p| p.parent().is_none_or(|parent| device(p) != device(parent)))
+            .and_then(|mount| mount.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "/".to_string())
+    }
+
+    fn list(&self) -> io::Result<Vec<PathBuf>> {
+        let mut walk = WalkDir::new(&self.root).follow_links(self.scan.follow_links);
+        if let Some(depth) = self.scan.max_depth {
+            walk = walk.max_depth(depth);
+        }
+        let mut ignore_files = IgnoreFiles::default();
+        Ok(walk
+            .into_iter()
+            .filter_entry(|e| {
+                let relative = e.path().strip_prefix(&self.root).unwrap_or(e.path());
+                !self.scan.excludes.is_excluded(relative)
+                    && !ignore_files.is_ignored(&self.root, e.path())
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .collect())
+    }
+
+    fn volume(&self) -> Option<VolumeInfo> {
+        volume::identify(&self.root)
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// 通过 `gphoto2` 命令行访问 PTP/MTP 设备（手机、不挂载为磁盘的相机）。
+pub struct Gphoto2Source {
+    port: Option<String>,
+    excludes: Excludes,
+    // 文件路径 -> (gphoto2 中的文件编号, 修改时间)
+    numbers: Mutex<HashMap<PathBuf, (u32, Option<SystemTime>)>>,
+    summary: OnceLock<DeviceSummary>,
+}
+
+/// `gphoto2 --summary` 中的型号和序列号。端口号 `usb:001,004` 每次插拔都会变，不能用来区分设备。
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DeviceSummary {
+    model: Option<String>,
+    serial: Option<String>,
+}
+
+// Camera summary:
+// Manufacturer: Apple Inc.
+// Model: iPhone
+//   Serial Number: 00008030001a2b3c
+fn parse_summary(output: &str) -> DeviceSummary {
+    let mut summary = DeviceSummary::default();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        match key.trim() {
+            "Model" if summary.model.is_none() => summary.model = value,
+            "Serial Number" if summary.serial.is_none() => summary.serial = value,
+            _ => {}
+        }
+    }
+    summary
+}
+
+impl Gphoto2Source {
+    pub fn new(port: Option<&str>, excludes: Excludes) -> Gphoto2Source {
+        Gphoto2Source {
+            port: port.map(str::to_string),
+            excludes,
+            numbers: Mutex::new(HashMap::new()),
+            summary: OnceLock::new(),
+        }
+    }
+
+    fn summary(&self) -> &DeviceSummary {
+        self.summary
+            .get_or_init(|| match self.command(&["--summary"]) {
+                Ok(output) => parse_summary(&String::from_utf8_lossy(&output)),
+                Err(e) => {
+                    eprintln!("无法读取设备信息：{e}");
+                    DeviceSummary::default()
+                }
+            })
+    }
+
+    fn command(&self, args: &[&str]) -> io::Result<Vec<u8>> {
+        let mut command = Command::new("gphoto2");
+        if let Some(port) = &self.port {
+            command.args(["--port", port]);
+        }
+        let output = command.args(args).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "gphoto2 {} 失败：{}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// `gphoto2 --get-file N --stdout` 的输出，边下载边读取，不在内存中保存整个文件。
+struct Gphoto2Download {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for Gphoto2Download {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        // 读到结尾时检查下载是否成功，失败的下载不能当作完整的文件
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("gphoto2 下载失败：{status}")));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for Gphoto2Download {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// 解析 `gphoto2 --list-files` 的输出：
+// There are 2 files in folder '/store_00010001/DCIM/100CANON':
+// #1     IMG_0001.JPG               rd  3512 KB 5184x3456 image/jpeg 1714567890
+//
+// 返回 (路径, 文件编号, 修改时间)，最后一列为修改时间的 Unix 时间戳
+fn parse_list_files(output: &str) -> Vec<(PathBuf, u32, Option<SystemTime>)> {
+    let mut folder = PathBuf::from("/");
+    let mut ret = Vec::new();
+    for line in output.lines() {
+        if let Some((_, rest)) = line.split_once("in folder '")
+            && let Some((name, _)) = rest.rsplit_once('\'')
+        {
+            folder = PathBuf::from(name);
+        } else if let Some(rest) = line.strip_prefix('#') {
+            let mut fields = rest.split_whitespace();
+            if let (Some(Ok(number)), Some(name)) =
+                (fields.next().map(str::parse::<u32>), fields.next())
+            {
+                let modified = fields
+                    .last()
+                    .and_then(|field| field.parse::<u64>().ok())
+                    .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+                ret.push((folder.join(name), number, modified));
+            }
+        }
+    }
+    ret
+}
+
+impl Source for Gphoto2Source {
+    fn describe(&self) -> String {
+        format!("gphoto2:{}", self.port.as_deref().unwrap_or(""))
+    }
+
+    fn volume_label(&self) -> String {
+        self.summary()
+            .model
+            .clone()
+            .unwrap_or_else(|| self.describe())
+    }
+
+    // 序列号作为 UUID，profile 的 volumes 和跳过列表因此能认出同一台设备
+    fn volume(&self) -> Option<VolumeInfo> {
+        let summary = self.summary();
+        if summary.serial.is_none() && summary.model.is_none() {
+            return None;
+        }
+        Some(VolumeInfo {
+            device: self.describe(),
+            uuid: summary.serial.clone(),
+            label: summary.model.clone(),
+            ..VolumeInfo::default()
+        })
+    }
+
+    fn list(&self) -> io::Result<Vec<PathBuf>> {
+        let output = self.command(&["--list-files"])?;
+        let mut files = parse_list_files(&String::from_utf8_lossy(&output));
+        files.retain(|(path, _, _)| !self.excludes.is_excluded(path));
+        let mut numbers = self.numbers.lock().unwrap();
+        numbers.extend(
+            files
+                .iter()
+                .map(|(path, number, modified)| (path.clone(), (*number, *modified))),
+        );
+        Ok(files.into_iter().map(|(path, _, _)| path).collect())
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        self.numbers.lock().unwrap().get(path)?.1
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        let (number, _) = self
+            .numbers
+            .lock()
+            .unwrap()
+            .get(path)
+            .copied()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "设备上没有这个文件"))?;
+        let mut command = Command::new("gphoto2");
+        if let Some(port) = &self.port {
+            command.args(["--port", port]);
+        }
+        let mut child = command
+            .args(["--quiet", "--get-file", &number.to_string(), "--stdout"])
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout 已设为管道");
+        Ok(Box::new(Gphoto2Download { child, stdout }))
+    }
+}
+
+/// 非本地来源的文件取到临时目录后，读取、复制流程与本地文件完全相同。
+///
+/// 目录结构与源中一致，XMP 附属文件因此仍能找到对应的照片。文件只在需要时取到本地：
+/// `probe` 逐个取出并立即决定去留，同一时间只有一个待定的文件占用空间。结束时删除临时目录。
+///
+/// 留下的文件直到导入结束才删除，应放在目标所在的文件系统中（`new_in`），
+/// 而不是可能很小的 tmpfs `/tmp`，目标的剩余空间检查因此也包含了它们。
+pub struct Staging {
+    // 名字随机、只有当前用户可访问，其他用户无法预先放置符号链接
+    dir: TempDir,
+    // 本地路径 -> (来源中的路径, 写入导入记录和报告的路径)
+    entries: Mutex<HashMap<PathBuf, (PathBuf, PathBuf)>>,
+}
+
+impl Staging {
+    /// 在系统临时目录中，用于不需要暂存的本地来源和测试。
+    pub fn new() -> io::Result<Staging> {
+        Staging::new_in(&std::env::temp_dir())
+    }
+
+    pub fn new_in(parent: &Path) -> io::Result<Staging> {
+        fs::create_dir_all(parent)?;
+        Ok(Staging {
+            dir: tempfile::Builder::new()
+                .prefix("photo_importer-")
+                .tempdir_in(parent)?,
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 列出 `wanted` 选中的文件，返回本地路径；本地来源直接返回原路径。
+    ///
+    /// 非本地来源的文件此时还没有取到本地，需要再 `fetch` 或 `probe`。
+    pub fn list(
+        &self,
+        source: &dyn Source,
+        wanted: &dyn Fn(&Path) -> bool,
+    ) -> io::Result<Vec<PathBuf>> {
+        let files = source.list()?.into_iter().filter(|p| wanted(p));
+        if source.is_local() {
+            return Ok(files.collect());
+        }
+        let describe = PathBuf::from(source.describe());
+        let mut entries = self.entries.lock().unwrap();
+        let mut ret = Vec::new();
+        for path in files {
+            match self.local_path(&path) {
+                Some(local) => {
+                    // 如 Takeout.zip/Takeout/Google Photos/IMG_1234.jpg
+                    let origin = describe.join(path.strip_prefix("/").unwrap_or(&path));
+                    entries.insert(local.clone(), (path, origin));
+                    ret.push(local);
+                }
+                None => eprintln!("跳过 {}：无效的路径", path.display()),
+            }
+        }
+        Ok(ret)
+    }
+
+    /// 把 `wanted` 选中、还不在本地的文件取到本地，返回读取失败的本地路径及原因。
+    pub fn fetch(
+        &self,
+        source: &dyn Source,
+        wanted: &dyn Fn(&Path) -> bool,
+    ) -> io::Result<Vec<(PathBuf, io::Error)>> {
+        self.probe(
+            source,
+            &|local| !local.exists() && wanted(local),
+            &mut |_| true,
+        )
+    }
+
+    /// 逐个把 `wanted` 选中的文件取到本地交给 `keep`，`keep` 返回 `false` 的随即删除。
+    ///
+    /// 返回读取失败（包括来源中没有读到）的本地路径及原因。
+    pub fn probe(
+        &self,
+        source: &dyn Source,
+        wanted: &dyn Fn(&Path) -> bool,
+        keep: &mut dyn FnMut(&Path) -> bool,
+    ) -> io::Result<Vec<(PathBuf, io::Error)>> {
+        // 来源中的路径 -> 本地路径
+        let locals = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(local, _)| wanted(local))
+            .map(|(local, (path, _))| (path.clone(), local.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut failed = Vec::new();
+        if locals.is_empty() {
+            return Ok(failed);
+        }
+        let mut visited = HashSet::new();
+        source.visit(&|path| locals.contains_key(path), &mut |path, reader| {
+            let local = &locals[path];
+            visited.insert(path.to_path_buf());
+            match write_local(local, reader, source.modified(path)) {
+                Ok(()) if !keep(local) => {
+                    let _ = fs::remove_file(local);
+                }
+                Ok(()) => {}
+                Err(e) => {
+                    eprintln!("读取失败 {}: {}", path.display(), e);
+                    failed.push((local.clone(), e));
+                }
+            }
+        })?;
+        for (path, local) in locals {
+            if !visited.contains(&path) {
+                let e = io::Error::new(io::ErrorKind::NotFound, "未能从来源读取");
+                failed.push((local, e));
+            }
+        }
+        Ok(failed)
+    }
+
+    /// 本地路径对应的来源中的文件，用于导入记录和报告，临时目录结束时会被删除；其他路径原样返回。
+    pub fn origin(&self, local: &Path) -> PathBuf {
+        match self.entries.lock().unwrap().get(local) {
+            Some((_, origin)) => origin.clone(),
+            None => local.to_path_buf(),
+        }
+    }
+
+    // 只保留普通的路径部分，压缩包中的 `..` 等不能写到临时目录之外
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part),
+                _ => None,
+            })
+            .collect::<PathBuf>();
+        match relative.as_os_str().is_empty() {
+            true => None,
+            false => Some(self.dir.path().join(relative)),
+        }
+    }
+}
+
+// 写入失败时删掉不完整的文件。访问和修改时间都设为来源中的修改时间，`--times source` 才能沿用它
+fn write_local(
+    local: &Path,
+    reader: &mut dyn Read,
+    modified: Option<SystemTime>,
+) -> io::Result<()> {
+    if let Some(parent) = local.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let result = File::create(local)
+        .and_then(|mut file| io::copy(reader, &mut file))
+        .and_then(|_| match modified {
+            Some(time) => {
+                let time = FileTime::from_system_time(time);
+                filetime::set_file_times(local, time, time)
+            }
+            None => Ok(()),
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(local);
+    }
+    result
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    pub fn detect(path: &Path) -> Option<ArchiveKind> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+// zip 中的时间没有时区，按本地时间解释
+fn zip_time(time: zip::DateTime) -> Option<SystemTime> {
+    let date = NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?;
+    let time = date.and_hms_opt(
+        time.hour() as u32,
+        time.minute() as u32,
+        time.second() as u32,
+    )?;
+    Some(Local.from_local_datetime(&time).earliest()?.into())
+}
+
+/// zip 或 tar 压缩包（如 Google Takeout），逐个条目流式读取，不整体解压。
+pub struct ArchiveSource {
+    path: PathBuf,
+    kind: ArchiveKind,
+    excludes: Excludes,
+    // 条目的修改时间，`visit` 读到条目时记下
+    modified: Mutex<HashMap<PathBuf, SystemTime>>,
+}
+
+impl ArchiveSource {
+    pub fn new(path: PathBuf, kind: ArchiveKind, excludes: Excludes) -> ArchiveSource {
+        ArchiveSource {
+            path,
+            kind,
+            excludes,
+            modified: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn tar(&self) -> io::Result<tar::Archive<Box<dyn Read>>> {
+        let file = File::open(&self.path)?;
+        let reader: Box<dyn Read> = match self.kind {
+            ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+            _ => Box::new(file),
+        };
+        Ok(tar::Archive::new(reader))
+    }
+
+    fn zip(&self) -> io::Result<ZipArchive<File>> {
+        ZipArchive::new(File::open(&self.path)?).map_err(io::Error::other)
+    }
+}
+
+impl Source for ArchiveSource {
+    fn describe(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    fn volume_label(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn list(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        match self.kind {
+            ArchiveKind::Zip => {
+                let zip = self.zip()?;
+                files.extend(
+                    zip.file_names()
+                        .filter(|name| !name.ends_with('/'))
+                        .map(PathBuf::from)
+                        .filter(|path| !self.excludes.is_excluded(path)),
+                );
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                for entry in self.tar()?.entries()? {
+                    let entry = entry?;
+                    let path = entry.path()?.into_owned();
+                    if entry.header().entry_type().is_file() && !self.excludes.is_excluded(&path) {
+                        files.push(path);
+                    }
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        self.modified.lock().unwrap().get(path).copied()
+    }
+
+    // 需要在遍历中找到这个条目并整个读出；导入流程通过 `visit` 顺序读取，不经过这里
+    fn read(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        let mut result = Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "压缩包中没有这个文件",
+        ));
+        self.visit(&|p| p == path, &mut |_, reader| {
+            let mut data = Vec::new();
+            result = reader.read_to_end(&mut data).map(|_| data);
+        })?;
+        Ok(Box::new(Cursor::new(result?)))
+    }
+
+    fn visit(
+        &self,
+        wanted: &dyn Fn(&Path) -> bool,
+        f: &mut dyn FnMut(&Path, &mut dyn Read),
+    ) -> io::Result<()> {
+        match self.kind {
+            ArchiveKind::Zip => {
+                let mut zip = self.zip()?;
+                for i in 0..zip.len() {
+                    let mut file = zip.by_index(i).map_err(io::Error::other)?;
+                    let path = PathBuf::from(file.name());
+                    if file.is_file() && !self.excludes.is_excluded(&path) && wanted(&path) {
+                        if let Some(time) = file.last_modified().and_then(zip_time) {
+                            self.modified.lock().unwrap().insert(path.clone(), time);
+                        }
+                        f(&path, &mut file);
+                    }
+                }
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                for entry in self.tar()?.entries()? {
+                    let mut entry = entry?;
+                    let path = entry.path()?.into_owned();
+                    if entry.header().entry_type().is_file()
+                        && !self.excludes.is_excluded(&path)
+                        && wanted(&path)
+                    {
+                        if let Ok(secs) = entry.header().mtime() {
+                            let time = UNIX_EPOCH + Duration::from_secs(secs);
+                            self.modified.lock().unwrap().insert(path.clone(), time);
+                        }
+                        f(&path, &mut entry);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 内存中的来源，用于测试。
+    pub struct MockSource {
+        pub files: HashMap<PathBuf, Vec<u8>>,
+    }
+
+    impl Source for MockSource {
+        fn describe(&self) -> String {
+            "mock".to_string()
+        }
+
+        fn list(&self) -> io::Result<Vec<PathBuf>> {
+            let mut files = self.files.keys().cloned().collect::<Vec<_>>();
+            files.sort();
+            Ok(files)
+        }
+
+        fn read(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+            let data = self.files.get(path).ok_or(io::ErrorKind::NotFound)?;
+            Ok(Box::new(data.as_slice()))
+        }
+
+        fn modified(&self, _path: &Path) -> Option<SystemTime> {
+            Some(UNIX_EPOCH + Duration::from_secs(1714567890))
+        }
+    }
+
+    #[test]
+    fn parses_device_summary() {
+        let output = "Camera summary:\n\
+            Manufacturer: Apple Inc.\n\
+            Model: iPhone\n  Version: 17.4\n\
+            \x20 Serial Number: 00008030001a2b3c\n\
+            Vendor Extension ID: 0x6 (1.0)\n";
+        assert_eq!(
+            parse_summary(output),
+            DeviceSummary {
+                model: Some("iPhone".to_string()),
+                serial: Some("00008030001a2b3c".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn stages_files_from_non_local_source() {
+        let output = "There is no file in folder '/'.\n\
+            There are 2 files in folder '/store_00010001/DCIM/100CANON':\n\
+            #1     IMG_0001.JPG               rd  3512 KB 5184x3456 image/jpeg 1714567890\n\
+            #2     IMG_0001.CR2               rd 24100 KB image/x-canon-cr2 1714567890\n";
+        assert_eq!(
+            parse_list_files(output),
+            [
+                (
+                    PathBuf::from("/store_00010001/DCIM/100CANON/IMG_0001.JPG"),
+                    1,
+                    Some(UNIX_EPOCH + Duration::from_secs(1714567890))
+                ),
+                (
+                    PathBuf::from("/store_00010001/DCIM/100CANON/IMG_0001.CR2"),
+                    2,
+                    Some(UNIX_EPOCH + Duration::from_secs(1714567890))
+                ),
+            ]
+        );
+
+        let source = MockSource {
+            files: HashMap::from([
+                (PathBuf::from("/DCIM/100/a.jpg"), b"jpeg".to_vec()),
+                (PathBuf::from("/DCIM/100/a.txt"), b"text".to_vec()),
+                (PathBuf::from("../escape.jpg"), b"jpeg".to_vec()),
+            ]),
+        };
+        let staging = Staging::new().unwrap();
+        let wanted = |p: &Path| p.extension().is_some_and(|e| e == "jpg");
+        let local = staging.list(&source, &wanted).unwrap();
+        assert_eq!(local.len(), 2);
+        assert!(
+            local
+                .iter()
+                .all(|p| p.starts_with(staging.dir.path()) && !p.exists())
+        );
+        let jpeg = local
+            .iter()
+            .find(|p| p.ends_with("DCIM/100/a.jpg"))
+            .unwrap()
+            .clone();
+        assert_eq!(staging.origin(&jpeg), Path::new("mock/DCIM/100/a.jpg"));
+
+        // 没有留下的文件随即删除，之后按需再取
+        let mut probed = Vec::new();
+        staging
+            .probe(&source, &|_| true, &mut |p| {
+                probed.push(fs::read(p).unwrap());
+                p != jpeg
+            })
+            .unwrap();
+        assert_eq!(probed, [b"jpeg", b"jpeg"]);
+        assert!(!jpeg.exists());
+        assert!(staging.fetch(&source, &|p| p == jpeg).unwrap().is_empty());
+        assert_eq!(fs::read(&jpeg).unwrap(), b"jpeg");
+        assert_eq!(
+            fs::metadata(&jpeg).unwrap().modified().unwrap(),
+            UNIX_EPOCH + Duration::from_secs(1714567890)
+        );
+        drop(staging);
+        assert!(!jpeg.exists());
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+// 目标目录可能还不存在，取最近一级已存在的上级目录
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("."))
+}
+
+/// 所在文件系统对非特权用户的可用空间（字节）。
+pub fn available_bytes(path: &Path) -> io::Result<u64> {
+    let path = CString::new(existing_ancestor(path).as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    // 字段类型随平台不同
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+pub fn device(path: &Path) -> io::Result<u64> {
+    Ok(fs::metadata(existing_ancestor(path))?.dev())
+}
+
+/// 空间不足的文件系统：(其上的第一个目标, 需要, 可用)。`destinations` 为 (目标, 需要的字节数)，
+/// 多个目标位于同一文件系统时需要的空间累加。
+pub fn check_free_space<'a>(
+    destinations: &[(&'a Path, u64)],
+) -> io::Result<Vec<(&'a Path, u64, u64)>> {
+    let mut by_device = HashMap::<u64, (&Path, u64)>::new();
+    let mut order = Vec::new();
+    for &(destination, required) in destinations {
+        let dev = device(destination)?;
+        let entry = by_device.entry(dev).or_insert_with(|| {
+            order.push(dev);
+            (destination, 0)
+        });
+        entry.1 += required;
+    }
+    let mut short = Vec::new();
+    for dev in order {
+        let (destination, required) = by_device[&dev];
+        let available = available_bytes(destination)?;
+        if available < required {
+            short.push((destination, required, available));
+        }
+    }
+    Ok(short)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{value:.1} {}", UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_space_on_missing_destinations() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let missing = dir.join("2024/05");
+        assert_eq!(device(&missing).unwrap(), device(dir).unwrap());
+        assert!(available_bytes(&missing).unwrap() > 0);
+
+        assert!(
+            check_free_space(&[(missing.as_path(), 0), (dir, 0)])
+                .unwrap()
+                .is_empty()
+        );
+        // 同一文件系统上的两个目标需要的空间累加
+        let half = u64::MAX / 2;
+        let short = check_free_space(&[(missing.as_path(), half), (dir, half)]).unwrap();
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].0, missing);
+        assert_eq!(short[0].1, u64::MAX / 2 * 2);
+        assert_eq!(format_bytes(1536), "1.5 KB");
+    }
+}
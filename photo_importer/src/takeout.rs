@@ -0,0 +1,75 @@
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutMetadata {
+    photo_taken_time: Option<TakeoutTime>,
+}
+
+#[derive(Deserialize)]
+struct TakeoutTime {
+    // Unix 时间戳（秒），以字符串保存
+    timestamp: String,
+}
+
+pub fn is_takeout_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+}
+
+// Takeout 的 JSON 附属文件名：IMG_1234.jpg.json、IMG_1234.jpg.supplemental-metadata.json，
+// 旧版本偶尔也会写成 IMG_1234.json
+fn json_candidates(path: &Path) -> Vec<PathBuf> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    [
+        format!("{name}.json"),
+        format!("{name}.supplemental-metadata.json"),
+        format!("{stem}.json"),
+    ]
+    .into_iter()
+    .map(|file_name| path.with_file_name(file_name))
+    .collect()
+}
+
+fn parse(json: &str) -> Option<NaiveDateTime> {
+    let metadata = serde_json::from_str::<TakeoutMetadata>(json).ok()?;
+    let timestamp = metadata.photo_taken_time?.timestamp.parse::<i64>().ok()?;
+    // 与 EXIF 一致，使用本地时间
+    let time = DateTime::from_timestamp(timestamp, 0)?;
+    Some(time.with_timezone(&Local).naive_local())
+}
+
+/// 从 Google Takeout 的 JSON 附属文件中读取 `photoTakenTime`。
+pub fn date_taken(path: &Path) -> Option<NaiveDateTime> {
+    json_candidates(path)
+        .iter()
+        .filter_map(|json| fs::read_to_string(json).ok())
+        .find_map(|json| parse(&json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_photo_taken_time() {
+        let json = r#"{
+            "title": "IMG_1234.jpg",
+            "photoTakenTime": {"timestamp": "1714567890", "formatted": "May 1, 2024, 12:51:30 PM UTC"}
+        }"#;
+        let expected = DateTime::from_timestamp(1714567890, 0)
+            .unwrap()
+            .with_timezone(&Local)
+            .naive_local();
+        assert_eq!(parse(json), Some(expected));
+        assert_eq!(parse(r#"{"title": "album"}"#), None);
+        assert_eq!(
+            json_candidates(Path::new("Takeout/Photos/IMG_1234.jpg"))[0],
+            Path::new("Takeout/Photos/IMG_1234.jpg.json")
+        );
+    }
+}
@@ -0,0 +1,8 @@
+// 各模块测试共用的辅助函数
+
+use chrono::NaiveDateTime;
+
+/// 解析 `2024-05-01T14:30:00` 形式的时间。
+pub fn dt(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+}
@@ -0,0 +1,166 @@
+use crate::metadata;
+use crate::xdg;
+use image::{DynamicImage, ImageFormat};
+use std::error::Error;
+use std::fs;
+use std::io::BufWriter;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tempfile::NamedTempFile;
+
+// freedesktop 规范中 large 尺寸
+const FREEDESKTOP_SIZE: u32 = 256;
+const FOLDER_SIZE: u32 = 320;
+pub const FOLDER_NAME: &str = ".thumbs";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThumbnailMode {
+    // ~/.cache/thumbnails/large/<md5(uri)>.png
+    Freedesktop,
+    // 与照片同目录的 .thumbs/<文件名>.jpg
+    Folder,
+}
+
+impl FromStr for ThumbnailMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "freedesktop" => Ok(ThumbnailMode::Freedesktop),
+            "folder" => Ok(ThumbnailMode::Folder),
+            _ => Err(format!("未知的缩略图模式：{s}，可选 freedesktop、folder")),
+        }
+    }
+}
+
+pub fn is_raw(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    matches!(ext.as_str(), "rw2" | "dng" | "nef")
+}
+
+pub fn is_jpeg(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    matches!(ext.as_str(), "jpg" | "jpeg")
+}
+
+// EXIF 方向：1 正常，2-8 依次为各种翻转、旋转
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate90().flipv(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// RAW 取最大的内嵌预览，JPEG 直接解码；其他格式返回 `None`。
+pub fn load_preview(path: &Path) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+    let reader = metadata::reader();
+    let image = if is_raw(path) {
+        let Some(data) = reader.preview(path) else {
+            return Ok(None);
+        };
+        image::load_from_memory(&data)?
+    } else if is_jpeg(path) {
+        image::open(path)?
+    } else {
+        return Ok(None);
+    };
+    let orientation = reader.orientation(path).unwrap_or(1);
+    Ok(Some(apply_orientation(image, orientation)))
+}
+
+// 按原始字节编码，非 UTF-8 的文件名也能得到正确的 URI
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
+
+fn freedesktop_dir() -> Option<PathBuf> {
+    let cache_home = xdg::xdg_dir("XDG_CACHE_HOME", ".cache")?;
+    Some(cache_home.join("thumbnails").join("large"))
+}
+
+fn write_freedesktop(path: &Path, thumb: &DynamicImage) -> Result<PathBuf, Box<dyn Error>> {
+    let path = fs::canonicalize(path)?;
+    let uri = file_uri(&path);
+    let dir = freedesktop_dir().ok_or("无法确定缩略图缓存目录")?;
+    fs::create_dir_all(&dir)?;
+    let thumb_path = dir.join(format!("{:x}.png", md5::compute(uri.as_bytes())));
+
+    // 规范要求先写临时文件再改名，权限为 0600，其他程序不会读到写了一半的缩略图
+    let temp = NamedTempFile::new_in(&dir)?;
+    let rgba = thumb.to_rgba8();
+    let mut encoder =
+        png::Encoder::new(BufWriter::new(temp.as_file()), rgba.width(), rgba.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.add_text_chunk("Thumb::URI".to_string(), uri)?;
+    encoder.add_text_chunk(
+        "Thumb::MTime".to_string(),
+        fs::metadata(&path)?.mtime().to_string(),
+    )?;
+    encoder.write_header()?.write_image_data(rgba.as_raw())?;
+    temp.persist(&thumb_path)?;
+    Ok(thumb_path)
+}
+
+fn write_folder(path: &Path, thumb: &DynamicImage) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = path.parent().unwrap_or(Path::new(".")).join(FOLDER_NAME);
+    fs::create_dir_all(&dir)?;
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".jpg");
+    let thumb_path = dir.join(name);
+    DynamicImage::ImageRgb8(thumb.to_rgb8()).save_with_format(&thumb_path, ImageFormat::Jpeg)?;
+    Ok(thumb_path)
+}
+
+/// 为已导入的文件生成缩略图；不支持的格式返回 `Ok(None)`。
+pub fn generate(mode: ThumbnailMode, path: &Path) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let Some(preview) = load_preview(path)? else {
+        return Ok(None);
+    };
+    let thumb_path = match mode {
+        ThumbnailMode::Freedesktop => {
+            write_freedesktop(path, &preview.thumbnail(FREEDESKTOP_SIZE, FREEDESKTOP_SIZE))?
+        }
+        ThumbnailMode::Folder => write_folder(path, &preview.thumbnail(FOLDER_SIZE, FOLDER_SIZE))?,
+    };
+    Ok(Some(thumb_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_is_percent_encoded() {
+        assert_eq!(
+            file_uri(Path::new("/home/danny/照片/a b.jpg")),
+            "file:///home/danny/%E7%85%A7%E7%89%87/a%20b.jpg"
+        );
+        let latin1 = std::ffi::OsStr::from_bytes(b"/photos/caf\xe9.jpg");
+        assert_eq!(file_uri(Path::new(latin1)), "file:///photos/caf%E9.jpg");
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use std::error::Error;
+use std::ops::Range;
+
+const DATE_FMT: &str = "%Y-%m-%d";
+// (格式, 精度)
+const DATE_TIME_FMTS: &[(&str, Duration)] = &[
+    ("%Y-%m-%dT%H:%M:%S", Duration::seconds(1)),
+    ("%Y-%m-%d %H:%M:%S", Duration::seconds(1)),
+    ("%Y-%m-%dT%H:%M", Duration::minutes(1)),
+];
+
+fn day_range(date: NaiveDate) -> Range<NaiveDateTime> {
+    let start = date.and_time(NaiveTime::MIN);
+    start..start + Duration::days(1)
+}
+
+fn month_range(year: i32, month: u32) -> Option<Range<NaiveDateTime>> {
+    let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let end = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    Some(start.and_time(NaiveTime::MIN)..end.and_time(NaiveTime::MIN))
+}
+
+/// 解析 `30m`、`12h`、`3d`、`2w` 这样的时长。
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let unit = input.chars().last()?;
+    let amount = input[..input.len() - unit.len_utf8()].parse::<i64>().ok()?;
+    match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+/// 带符号的时长，如 `+1h`、`-30m`，用于校正相机时钟。
+pub fn parse_signed_duration(input: &str) -> Option<Duration> {
+    let input = input.trim();
+    match input.strip_prefix('-') {
+        Some(rest) => parse_duration(rest).map(|d| -d),
+        None => parse_duration(input.strip_prefix('+').unwrap_or(input)),
+    }
+}
+
+fn parse_iso_week(input: &str) -> Option<Range<NaiveDateTime>> {
+    let (year, week) = input.split_once("-W").or_else(|| input.split_once("-w"))?;
+    let monday = NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)?;
+    let start = monday.and_time(NaiveTime::MIN);
+    Some(start..start + Duration::weeks(1))
+}
+
+/// 把时间表达式解析为一个半开区间。
+///
+/// 日期、月份（`2024-05`）、年份、ISO 周（`2024-W18`）、`today`、`yesterday` 覆盖整段时间；
+/// 相对时间（`3d`、`12h`、`2w`、`30m`）表示从 `now` 往前的一段时间；
+/// 精确时刻覆盖它所写到的那一秒或一分钟，如 `2024-05-01T14:30` 为 14:30:00 到 14:31:00。
+pub fn parse_time_expr(
+    input: &str,
+    now: NaiveDateTime,
+) -> Result<Range<NaiveDateTime>, Box<dyn Error>> {
+    let input = input.trim();
+    match input.to_lowercase().as_str() {
+        "today" => return Ok(day_range(now.date())),
+        "yesterday" => return Ok(day_range(now.date() - Duration::days(1))),
+        _ => {}
+    }
+    if let Some(duration) = parse_duration(input) {
+        return Ok(now - duration..now);
+    }
+    for (fmt, precision) in DATE_TIME_FMTS {
+        if let Ok(time) = NaiveDateTime::parse_from_str(input, fmt) {
+            return Ok(time..time + *precision);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, DATE_FMT) {
+        return Ok(day_range(date));
+    }
+    if let Some(range) = parse_iso_week(input) {
+        return Ok(range);
+    }
+    if let Some((year, month)) = input.split_once('-')
+        && let (Ok(year), Ok(month)) = (year.parse(), month.parse())
+        && let Some(range) = month_range(year, month)
+    {
+        return Ok(range);
+    }
+    if input.len() == 4
+        && let Ok(year) = input.parse::<i32>()
+        && let (Some(start), Some(end)) = (month_range(year, 1), month_range(year, 12))
+    {
+        return Ok(start.start..end.end);
+    }
+    Err(format!("无法识别的时间表达式：{input}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dt;
+
+    #[test]
+    fn date_covers_whole_day() {
+        let now = dt("2024-06-01T12:00:00");
+        let range = parse_time_expr("2024-05-01", now).unwrap();
+        assert_eq!(range, dt("2024-05-01T00:00:00")..dt("2024-05-02T00:00:00"));
+        assert!(range.contains(&dt("2024-05-01T23:59:59")));
+    }
+
+    #[test]
+    fn months_weeks_and_years() {
+        let now = dt("2024-06-01T12:00:00");
+        assert_eq!(
+            parse_time_expr("2024-12", now).unwrap(),
+            dt("2024-12-01T00:00:00")..dt("2025-01-01T00:00:00")
+        );
+        assert_eq!(
+            parse_time_expr("2024-W18", now).unwrap(),
+            dt("2024-04-29T00:00:00")..dt("2024-05-06T00:00:00")
+        );
+        assert_eq!(
+            parse_time_expr("2023", now).unwrap(),
+            dt("2023-01-01T00:00:00")..dt("2024-01-01T00:00:00")
+        );
+    }
+
+    #[test]
+    fn relative_and_named() {
+        let now = dt("2024-06-01T12:00:00");
+        assert_eq!(
+            parse_time_expr("3d", now).unwrap(),
+            dt("2024-05-29T12:00:00")..now
+        );
+        assert_eq!(
+            parse_time_expr("yesterday", now).unwrap(),
+            dt("2024-05-31T00:00:00")..dt("2024-06-01T00:00:00")
+        );
+        assert_eq!(
+            parse_time_expr("2024-05-01T08:30:00", now).unwrap(),
+            dt("2024-05-01T08:30:00")..dt("2024-05-01T08:30:01")
+        );
+        assert_eq!(
+            parse_time_expr("2024-05-01T08:30", now).unwrap(),
+            dt("2024-05-01T08:30:00")..dt("2024-05-01T08:31:00")
+        );
+        assert!(parse_time_expr("next tuesday", now).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::journal::JournalEntry;
+use crate::{scheduler, thumbnail};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UndoSummary {
+    pub removed: usize,
+    pub restored: usize,
+    pub skipped: usize,
+}
+
+// 文件内容是否仍与记录中的哈希一致
+fn matches_hash(path: &Path, hash: &str) -> Result<bool, String> {
+    if hash.is_empty() {
+        return Err("记录中没有哈希，无法校验".to_string());
+    }
+    match scheduler::hash_file(path) {
+        Ok(actual) => Ok(actual.to_hex().as_str() == hash),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// 按导入记录倒序撤销：删除导入新建且未被改动过的文件，删除变空的新建目录，
+/// 把 reorganize 移动过的文件移回原处。内容已变的文件会被保留。
+pub fn undo(entries: &[JournalEntry]) -> UndoSummary {
+    // 每个文件最后一次写入后的哈希，以及本次导入新建的文件
+    let mut expected = HashMap::new();
+    let mut created = HashSet::new();
+    for entry in entries {
+        match entry {
+            JournalEntry::Copied {
+                destination: path,
+                hash,
+                ..
+            }
+            | JournalEntry::Created { path, hash } => {
+                expected.insert(path.clone(), hash.clone());
+                created.insert(path.clone());
+            }
+            JournalEntry::Updated { path, hash } => {
+                expected.insert(path.clone(), hash.clone());
+            }
+            _ => {}
+        }
+    }
+
+    let mut summary = UndoSummary::default();
+    let mut handled = HashSet::new();
+    for entry in entries.iter().rev() {
+        match entry {
+            JournalEntry::Copied {
+                destination: path, ..
+            }
+            | JournalEntry::Created { path, .. } => {
+                if !handled.insert(path.clone()) || !path.exists() {
+                    continue;
+                }
+                match matches_hash(path, &expected[path]) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        println!("导入后已被修改，保留: {}", path.display());
+                        summary.skipped += 1;
+                        continue;
+                    }
+                    Err(e) => {
+                        println!("无法校验，保留 {}: {}", path.display(), e);
+                        summary.skipped += 1;
+                        continue;
+                    }
+                }
+                if let Err(e) = fs::remove_file(path) {
+                    eprintln!("删除失败 {}: {}", path.display(), e);
+                    summary.skipped += 1;
+                    continue;
+                }
+                println!("已删除: {}", path.display());
+                summary.removed += 1;
+                // 缩略图目录由缩略图生成时顺带创建
+                if let Some(parent) = path.parent()
+                    && parent.file_name() == Some(thumbnail::FOLDER_NAME.as_ref())
+                {
+                    let _ = fs::remove_dir(parent);
+                }
+            }
+            JournalEntry::Moved { from, to, hash } => {
+                if from.exists() {
+                    println!("原位置已有文件，跳过: {}", from.display());
+                    summary.skipped += 1;
+                    continue;
+                }
+                if !matches!(matches_hash(to, hash), Ok(true)) {
+                    println!("文件不存在或已被修改，跳过: {}", to.display());
+                    summary.skipped += 1;
+                    continue;
+                }
+                let result = from
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::rename(to, from));
+                if let Err(e) = result {
+                    eprintln!("移回失败 {}: {}", to.display(), e);
+                    summary.skipped += 1;
+                    continue;
+                }
+                println!("已移回: {} -> {}", to.display(), from.display());
+                summary.restored += 1;
+            }
+            JournalEntry::CreatedDir { path } => {
+                // 目录中还有其他文件时 remove_dir 会失败，正好保留
+                if fs::remove_dir(path).is_ok() {
+                    println!("已删除空目录: {}", path.display());
+                }
+            }
+            // 不是本次导入新建的文件，无法还原修改前的内容
+            JournalEntry::Updated { path, .. } => {
+                if !created.contains(path) && handled.insert(path.clone()) {
+                    println!("导入时修改过，无法还原: {}", path.display());
+                    summary.skipped += 1;
+                }
+            }
+            JournalEntry::Started { .. } => {}
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::{Journal, read_journal};
+    use chrono::NaiveDateTime;
+
+    fn hash(path: &Path) -> String {
+        scheduler::hash_file(path).unwrap().to_hex().to_string()
+    }
+
+    #[test]
+    fn removes_unchanged_files_and_restores_moves() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let time = NaiveDateTime::default();
+        let mut journal = Journal::create(root, time, Path::new("/card"), "card").unwrap();
+
+        let dir = root.join("2024/2024-05-01");
+        journal.create_dir_all(&dir).unwrap();
+        let (copied, edited) = (dir.join("a.jpg"), dir.join("b.jpg"));
+        fs::write(&copied, "a").unwrap();
+        fs::write(&edited, "b").unwrap();
+        for path in [&copied, &edited] {
+            journal
+                .append(&JournalEntry::Copied {
+                    source: Path::new("/card").join(path.file_name().unwrap()),
+                    destination: path.clone(),
+                    hash: hash(path),
+                })
+                .unwrap();
+        }
+        let (from, to) = (root.join("misc/c.jpg"), root.join("c.jpg"));
+        fs::write(&to, "c").unwrap();
+        journal
+            .append(&JournalEntry::Moved {
+                from: from.clone(),
+                to: to.clone(),
+                hash: hash(&to),
+            })
+            .unwrap();
+        fs::write(&edited, "edited").unwrap();
+
+        let summary = undo(&read_journal(journal.path()).unwrap());
+        assert_eq!(
+            summary,
+            UndoSummary {
+                removed: 1,
+                restored: 1,
+                skipped: 1,
+            }
+        );
+        assert!(!copied.exists());
+        assert!(edited.exists());
+        assert!(from.exists() && !to.exists());
+        // 目录中还有保留的文件，不会被删除
+        assert!(dir.exists());
+    }
+}
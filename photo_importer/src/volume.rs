@@ -0,0 +1,167 @@
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// 来源所在的卷（存储卡、U 盘分区）。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VolumeInfo {
+    pub mount_point: PathBuf,
+    // 如 /dev/sdb1
+    pub device: String,
+    // 文件系统 UUID，FAT/exFAT 为 E956-B7F2 这样的卷序列号
+    pub uuid: Option<String>,
+    pub label: Option<String>,
+}
+
+impl VolumeInfo {
+    /// 用于区分存储卡的标识：有 UUID 用 UUID，否则用卷标
+    pub fn id(&self) -> Option<&str> {
+        self.uuid.as_deref().or(self.label.as_deref())
+    }
+
+    /// `name` 是否为此卷的 UUID 或卷标
+    pub fn matches(&self, name: &str) -> bool {
+        self.uuid
+            .as_deref()
+            .is_some_and(|uuid| uuid.eq_ignore_ascii_case(name))
+            || self.label.as_deref() == Some(name)
+    }
+}
+
+struct Mount {
+    // 主、次设备号
+    device_number: (u32, u32),
+    mount_point: PathBuf,
+    source: String,
+}
+
+// mountinfo 中的空格等写成 \040 这样的八进制转义
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(code) = field
+                .get(i + 1..i + 4)
+                .and_then(|oct| u8::from_str_radix(oct, 8).ok())
+        {
+            out.push(code);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// /dev/disk/by-label 中的空格等写成 \x20 这样的十六进制转义
+fn unescape_hex(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if name.get(i..i + 2) == Some("\\x")
+            && let Some(code) = name
+                .get(i + 2..i + 4)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(code);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// 格式见 proc(5)：ID 父ID 主:次 根 挂载点 选项 [可选字段...] - 类型 来源 超级块选项
+fn parse_mountinfo(text: &str) -> Vec<Mount> {
+    let mut mounts = Vec::new();
+    for line in text.lines() {
+        let fields = line.split(' ').collect::<Vec<_>>();
+        let Some(separator) = fields.iter().position(|f| *f == "-") else {
+            continue;
+        };
+        let (Some(numbers), Some(mount_point), Some(source)) =
+            (fields.get(2), fields.get(4), fields.get(separator + 2))
+        else {
+            continue;
+        };
+        let Some((major, minor)) = numbers.split_once(':') else {
+            continue;
+        };
+        let (Ok(major), Ok(minor)) = (major.parse(), minor.parse()) else {
+            continue;
+        };
+        mounts.push(Mount {
+            device_number: (major, minor),
+            mount_point: PathBuf::from(unescape_octal(mount_point)),
+            source: unescape_octal(source),
+        });
+    }
+    mounts
+}
+
+// /dev/disk/by-uuid 等目录中指向该设备的链接名
+fn find_link(dir: &str, device_number: (u32, u32)) -> Option<String> {
+    let device = libc::makedev(device_number.0, device_number.1);
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| {
+            fs::metadata(e.path())
+                .is_ok_and(|m| m.file_type().is_block_device() && m.rdev() == device)
+        })
+        .map(|e| unescape_hex(&e.file_name().to_string_lossy()))
+}
+
+/// 找出 `path` 所在的挂载点，并通过 `/dev/disk/by-uuid`、`/dev/disk/by-label` 查出卷的 UUID 和卷标。
+pub fn identify(path: &Path) -> Option<VolumeInfo> {
+    let path = fs::canonicalize(path).ok()?;
+    let mounts = parse_mountinfo(&fs::read_to_string("/proc/self/mountinfo").ok()?);
+    // 最长的挂载点前缀，即最内层的挂载
+    let mount = mounts
+        .iter()
+        .filter(|m| path.starts_with(&m.mount_point))
+        .max_by_key(|m| m.mount_point.components().count())?;
+    Some(VolumeInfo {
+        mount_point: mount.mount_point.clone(),
+        device: mount.source.clone(),
+        uuid: find_link("/dev/disk/by-uuid", mount.device_number),
+        label: find_link("/dev/disk/by-label", mount.device_number),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mountinfo() {
+        let text = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+97 22 8:17 / /run/media/danny/NIKON\\040D750 rw,nosuid shared:52 - exfat /dev/sdb1 rw";
+        let mounts = parse_mountinfo(text);
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[1].device_number, (8, 17));
+        assert_eq!(
+            mounts[1].mount_point,
+            Path::new("/run/media/danny/NIKON D750")
+        );
+        assert_eq!(mounts[1].source, "/dev/sdb1");
+        assert_eq!(unescape_hex("NIKON\\x20D750"), "NIKON D750");
+
+        let volume = VolumeInfo {
+            uuid: Some("E956-B7F2".to_string()),
+            label: Some("NIKON D750".to_string()),
+            ..Default::default()
+        };
+        assert!(volume.matches("e956-b7f2"));
+        assert!(volume.matches("NIKON D750"));
+        assert_eq!(volume.id(), Some("E956-B7F2"));
+    }
+}
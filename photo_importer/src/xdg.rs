@@ -0,0 +1,9 @@
+use std::path::{Path, PathBuf};
+
+/// XDG 基础目录，如 `xdg_dir("XDG_DATA_HOME", ".local/share")`；变量未设置或为空时取 `$HOME` 下的 `fallback`。
+pub fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(fallback)))
+}
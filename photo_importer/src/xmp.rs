@@ -0,0 +1,129 @@
+#![cfg_attr(not(feature = "exiv2"), allow(dead_code, unused_imports))]
+
+use crate::thumbnail;
+#[cfg(feature = "exiv2")]
+use rexiv2::Metadata;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const NAMESPACE: &str = "https://github.com/DanielLiu-326/best_practice/photo_importer/1.0/";
+const PREFIX: &str = "photoimporter";
+
+// exiv2 只能打开已存在的 XMP 文件，新建附属文件时先写入一个空包
+const EMPTY_PACKET: &str = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"/>
+</x:xmpmeta>
+<?xpacket end=\"w\"?>
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmpMode {
+    // 写入 <文件名>.<扩展名>.xmp 附属文件
+    Sidecar,
+    // JPEG 直接写入文件本身（同时写 Exif/IPTC），其他格式仍写附属文件
+    Embed,
+}
+
+impl FromStr for XmpMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sidecar" => Ok(XmpMode::Sidecar),
+            "embed" => Ok(XmpMode::Embed),
+            _ => Err(format!("未知的 XMP 模式：{s}，可选 sidecar、embed")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum XmpValue {
+    Text(String),
+    // 无序列表，如 dc:subject 关键词
+    Bag(Vec<String>),
+}
+
+/// 注册本程序的 XMP 命名空间，需在写入 `Xmp.photoimporter.*` 之前调用一次。
+#[cfg(feature = "exiv2")]
+pub fn register_namespace() -> Result<(), Box<dyn Error>> {
+    rexiv2::register_xmp_namespace(NAMESPACE, PREFIX)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "exiv2"))]
+pub fn register_namespace() -> Result<(), Box<dyn Error>> {
+    Err(format!("编译时未启用 exiv2 功能，无法写入 XMP（{NAMESPACE}）").into())
+}
+
+pub fn tag(name: &str) -> String {
+    format!("Xmp.{PREFIX}.{name}")
+}
+
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".xmp");
+    path.with_file_name(name)
+}
+
+/// 把标签写入 `path` 或其附属文件，返回实际被修改的文件以及它是否为新建。
+#[cfg(feature = "exiv2")]
+pub fn write(
+    mode: XmpMode,
+    path: &Path,
+    values: &[(String, XmpValue)],
+) -> Result<(PathBuf, bool), Box<dyn Error>> {
+    let (target, created) = if mode == XmpMode::Embed && thumbnail::is_jpeg(path) {
+        (path.to_path_buf(), false)
+    } else {
+        let sidecar = sidecar_path(path);
+        let created = !sidecar.exists();
+        if created {
+            fs::write(&sidecar, EMPTY_PACKET)?;
+        }
+        (sidecar, created)
+    };
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        let metadata = Metadata::new_from_path(&target)?;
+        for (tag, value) in values {
+            // 附属文件只能保存 XMP，Exif/IPTC 标签仅在写入文件本身时生效
+            if !tag.starts_with("Xmp.") && target != path {
+                continue;
+            }
+            match value {
+                XmpValue::Text(text) => metadata.set_tag_string(tag, text)?,
+                XmpValue::Bag(items) => {
+                    // 与已有的值合并，而不是覆盖
+                    let mut merged = metadata.get_tag_multiple_strings(tag).unwrap_or_default();
+                    for item in items {
+                        if !merged.contains(item) {
+                            merged.push(item.clone());
+                        }
+                    }
+                    let merged = merged.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                    metadata.set_tag_multiple_strings(tag, &merged)?
+                }
+            }
+        }
+        metadata.save_to_file(&target)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        if created {
+            let _ = fs::remove_file(&target);
+        }
+        return Err(e);
+    }
+    Ok((target, created))
+}
+
+#[cfg(not(feature = "exiv2"))]
+pub fn write(
+    _mode: XmpMode,
+    _path: &Path,
+    _values: &[(String, XmpValue)],
+) -> Result<(PathBuf, bool), Box<dyn Error>> {
+    Err("编译时未启用 exiv2 功能，无法写入 XMP".into())
+}